@@ -0,0 +1,71 @@
+// Local stand-in for a remote signer, for trying SIGNER_TYPE=remote without Web3Signer.
+// Serves eth_accounts, eth_sign and eth_signTransaction over JSON-RPC using a key from PRIVATE_KEY.
+//
+//   PRIVATE_KEY=0x... cargo run --example mock_remote_signer
+//   SIGNER_TYPE=remote REMOTE_SIGNER_URL=http://127.0.0.1:9000 REMOTE_SIGNER_ADDRESS=0x... cargo run
+//
+// Also compiled into the signer unit tests, which serve it on an ephemeral port.
+use ethers::prelude::*;
+use ethers::types::transaction::eip2718::TypedTransaction;
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Request, Response, Server};
+use serde_json::{json, Value};
+use std::convert::Infallible;
+use std::env;
+use std::net::TcpListener;
+
+async fn handle(wallet: LocalWallet, request: Request<Body>) -> Result<Response<Body>, Infallible> {
+    let body = hyper::body::to_bytes(request.into_body()).await.unwrap_or_default();
+    let request: Value = serde_json::from_slice(&body).unwrap_or(Value::Null);
+    let id = request["id"].clone();
+    let method = request["method"].as_str().unwrap_or_default().to_string();
+    println!("{} request", method);
+
+    let result: Result<Value, String> = match method.as_str() {
+        "eth_accounts" => Ok(json!([wallet.address()])),
+        "eth_sign" => match request["params"][1].as_str().map(|data| data.parse::<Bytes>()) {
+            Some(Ok(data)) => wallet.sign_message(data.as_ref()).await
+                .map(|signature| json!(Bytes::from(signature.to_vec())))
+                .map_err(|e| e.to_string()),
+            _ => Err("invalid eth_sign params".to_string()),
+        },
+        "eth_signTransaction" => match serde_json::from_value::<TypedTransaction>(request["params"][0].clone()) {
+            Ok(tx) => {
+                let chain_id = tx.chain_id().map(|id| id.as_u64()).unwrap_or(wallet.chain_id());
+                let wallet = wallet.clone().with_chain_id(chain_id);
+                wallet.sign_transaction(&tx).await
+                    .map(|signature| json!(tx.rlp_signed(&signature)))
+                    .map_err(|e| e.to_string())
+            }
+            Err(e) => Err(format!("invalid transaction: {}", e)),
+        },
+        _ => Err(format!("method {} not supported", method)),
+    };
+
+    let response = match result {
+        Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+        Err(message) => json!({ "jsonrpc": "2.0", "id": id, "error": { "code": -32000, "message": message } }),
+    };
+    Ok(Response::new(Body::from(response.to_string())))
+}
+
+// Serve the mock on an already bound listener until the process exits
+pub async fn serve(wallet: LocalWallet, listener: TcpListener) -> Result<(), hyper::Error> {
+    let make_service = make_service_fn(move |_| {
+        let wallet = wallet.clone();
+        async move { Ok::<_, Infallible>(service_fn(move |request| handle(wallet.clone(), request))) }
+    });
+    Server::from_tcp(listener)?.serve(make_service).await
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    dotenv::dotenv().ok();
+    let wallet: LocalWallet = env::var("PRIVATE_KEY").expect("PRIVATE_KEY must be set").parse()?;
+    let addr = env::var("MOCK_SIGNER_ADDR").unwrap_or_else(|_| "127.0.0.1:9000".to_string());
+    let listener = TcpListener::bind(&addr)?;
+
+    println!("Mock remote signer listening on http://{}", listener.local_addr()?);
+    serve(wallet, listener).await?;
+    Ok(())
+}
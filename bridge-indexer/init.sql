@@ -5,18 +5,33 @@ CREATE EXTENSION IF NOT EXISTS "uuid-ossp";
 CREATE TABLE deposits (
     deposit_id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
     chain_id VARCHAR(50) NOT NULL, -- e.g., "holesky" or "target_chain"
-    transaction_hash VARCHAR(66) NOT NULL UNIQUE, -- Ethereum tx hash (0x + 64 chars)
+    transaction_hash BYTEA NOT NULL CONSTRAINT deposits_transaction_hash_length CHECK (octet_length(transaction_hash) = 32), -- Ethereum tx hash
+    log_index BIGINT, -- Position of the Deposit log in its block
+    tx_index BIGINT, -- Position of the transaction in its block
     block_number BIGINT NOT NULL, -- Block where the event occurred
-    token_address VARCHAR(42) NOT NULL, -- Token contract address (0x + 40 chars)
-    from_address VARCHAR(42) NOT NULL, -- Sender address
-    to_address VARCHAR(42) NOT NULL, -- Recipient address on target chain
-    amount TEXT NOT NULL, -- Changed from NUMERIC to TEXT to handle large blockchain values
-    nonce TEXT NOT NULL, -- Unique nonce from Deposit event
+    block_hash BYTEA CONSTRAINT deposits_block_hash_length CHECK (octet_length(block_hash) = 32), -- Hash of that block, to detect reorgs
+    block_timestamp TIMESTAMP WITH TIME ZONE, -- On-chain time of the deposit
+    bridge_address BYTEA CONSTRAINT deposits_bridge_address_length CHECK (octet_length(bridge_address) = 20), -- Bridge contract that emitted the Deposit log
+    token_address BYTEA NOT NULL CONSTRAINT deposits_token_address_length CHECK (octet_length(token_address) = 20), -- Token contract address
+    from_address BYTEA NOT NULL CONSTRAINT deposits_from_address_length CHECK (octet_length(from_address) = 20), -- Sender address
+    to_address BYTEA NOT NULL CONSTRAINT deposits_to_address_length CHECK (octet_length(to_address) = 20), -- Recipient address on target chain
+    amount NUMERIC(78,0) NOT NULL, -- uint256 in source token units (78 digits hold any uint256)
+    nonce NUMERIC(78,0) NOT NULL, -- Nonce from Deposit event (same-nonce rows are marked 'ambiguous', not rejected)
     processed BOOLEAN DEFAULT FALSE, -- Whether distribution has occurred
     finality_confirmed BOOLEAN DEFAULT FALSE, -- Whether block finality is reached
+    distribution_tx_hash BYTEA CONSTRAINT deposits_distribution_tx_hash_length CHECK (octet_length(distribution_tx_hash) = 32), -- Last distribute tx sent for this deposit
+    revert_reason TEXT, -- Why the last distribution attempt reverted
+    distribution_attempts INTEGER NOT NULL DEFAULT 0, -- Distribute txs that reverted on-chain
+    status VARCHAR(20) NOT NULL DEFAULT 'pending', -- 'pending' until distributed or failed for good ('failed'); 'held_for_review' when over a rate limit, 'awaiting_approval' when above the approval threshold, 'blocked' by address screening, 'rejected' when the amount cannot be converted, 'ambiguous' when its distribution id collides
+    status_reason TEXT, -- Why the deposit is held, awaiting approval or blocked
+    limits_waived BOOLEAN NOT NULL DEFAULT FALSE, -- Released by an operator past the rate limits
+    distribution_id BYTEA UNIQUE CONSTRAINT deposits_distribution_id_length CHECK (octet_length(distribution_id) = 32), -- keccak256(abi.encode(source chain id, source bridge, nonce)), passed to distribute
+    distribution_amount NUMERIC(78,0), -- Amount distributed after fees, in destination token units
+    dust NUMERIC(78,0), -- Remainder lost converting to a token with fewer decimals, in source token units
+    legacy_nonce BOOLEAN NOT NULL DEFAULT FALSE, -- Indexed before distribution ids, so it may have been distributed under the raw nonce
     created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
     updated_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
-    CONSTRAINT unique_nonce_chain UNIQUE (nonce, chain_id)
+    CONSTRAINT unique_chain_tx_log UNIQUE (chain_id, transaction_hash, log_index)
 );
 
 -- Distributions Table: Stores distribution events on the target chain
@@ -36,8 +51,56 @@ CREATE TABLE distributions (
     CONSTRAINT unique_nonce_chain_dist UNIQUE (nonce, chain_id)
 );
 
+-- Distribution Transactions Table: Every distribute tx broadcast for a deposit, including fee-bumped replacements
+CREATE TABLE distribution_transactions (
+    tx_hash BYTEA PRIMARY KEY CONSTRAINT distribution_transactions_tx_hash_length CHECK (octet_length(tx_hash) = 32), -- Distribution tx hash
+    deposit_id UUID NOT NULL REFERENCES deposits(deposit_id), -- Links to deposit
+    chain_id VARCHAR(50) NOT NULL, -- Target chain ID
+    account_nonce BIGINT NOT NULL, -- Signer account nonce shared by a tx and its replacements
+    max_fee_per_gas TEXT NOT NULL, -- In wei
+    max_priority_fee_per_gas TEXT NOT NULL, -- In wei
+    replaces_tx_hash BYTEA CONSTRAINT distribution_transactions_replaces_tx_hash_length CHECK (octet_length(replaces_tx_hash) = 32), -- Tx this one replaced with higher fees
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP
+);
+
+-- Deposit Approvals Table: Audit trail of operator approvals for large deposits
+CREATE TABLE deposit_approvals (
+    deposit_id UUID NOT NULL REFERENCES deposits(deposit_id), -- Links to deposit
+    approver BYTEA NOT NULL CONSTRAINT deposit_approvals_approver_length CHECK (octet_length(approver) = 20), -- Operator address from APPROVERS
+    signature TEXT NOT NULL, -- Operator's EIP-191 signature over the approval message
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (deposit_id, approver)
+);
+
+-- Fees Table: Bridge fee kept from each distributed deposit
+CREATE TABLE fees (
+    deposit_id UUID PRIMARY KEY REFERENCES deposits(deposit_id), -- Links to deposit
+    chain_id VARCHAR(50) NOT NULL, -- Destination chain ID
+    token_address BYTEA NOT NULL CONSTRAINT fees_token_address_length CHECK (octet_length(token_address) = 20), -- Destination token the fee is kept in
+    fee_model VARCHAR(10) NOT NULL, -- 'flat', 'bps' or 'gas'
+    amount NUMERIC(78,0) NOT NULL, -- Fee, in destination token units
+    gross_amount NUMERIC(78,0) NOT NULL, -- Amount before the fee, in destination token units
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP
+);
+
+-- Ignored Deposits Table: Deposits in tokens the indexer does not bridge, so gap detection does not report their nonces
+CREATE TABLE ignored_deposits (
+    chain_id VARCHAR(50) NOT NULL, -- Source chain ID
+    nonce NUMERIC(78,0) NOT NULL, -- Nonce from Deposit event
+    token_address BYTEA NOT NULL CONSTRAINT ignored_deposits_token_address_length CHECK (octet_length(token_address) = 20), -- Token that is not bridged
+    transaction_hash BYTEA NOT NULL CONSTRAINT ignored_deposits_transaction_hash_length CHECK (octet_length(transaction_hash) = 32), -- Deposit tx hash
+    block_number BIGINT NOT NULL, -- Block where the event occurred
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (chain_id, nonce)
+);
+
 -- Indexes for Performance
 CREATE INDEX idx_deposits_chain_block_nonce ON deposits(chain_id, block_number, nonce);
 CREATE INDEX idx_deposits_processed_finality ON deposits(processed, finality_confirmed);
+CREATE INDEX idx_deposits_transaction_hash ON deposits(transaction_hash);
+CREATE INDEX idx_deposits_chain_nonce ON deposits(chain_id, nonce);
+CREATE INDEX idx_deposits_from_address ON deposits(from_address, created_at);
+CREATE INDEX idx_deposits_to_address ON deposits(to_address, created_at);
 CREATE INDEX idx_distributions_deposit_id ON distributions(deposit_id);
-CREATE INDEX idx_distributions_status ON distributions(status);
\ No newline at end of file
+CREATE INDEX idx_distributions_status ON distributions(status);
+CREATE INDEX idx_distribution_transactions_deposit_id ON distribution_transactions(deposit_id);
\ No newline at end of file
@@ -1,4 +1,13 @@
 use ethers::prelude::*;
 
 // Use the abigen! macro to generate the contract bindings
-abigen!(TokenBridge, "abis/TokenBridge.json");
\ No newline at end of file
+abigen!(TokenBridge, "abis/TokenBridge.json");
+
+// The part of the ERC-20 interface the indexer reads from bridged tokens
+abigen!(
+    ERC20,
+    r#"[
+        function balanceOf(address account) external view returns (uint256)
+        function decimals() external view returns (uint8)
+    ]"#
+);
@@ -0,0 +1,28 @@
+use serde_json::json;
+use std::env;
+use std::sync::OnceLock;
+use std::time::Duration;
+
+// Callers await the alert inline, so a slow webhook must not hold them up for long
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(5);
+
+// Raise an operator alert: always logged, and also POSTed as `{"text": ...}` to
+// ALERT_WEBHOOK_URL when set (the payload Slack/Mattermost incoming webhooks accept).
+pub async fn raise(message: &str) {
+    eprintln!("ALERT: {}", message);
+    let url = match env::var("ALERT_WEBHOOK_URL") {
+        Ok(url) if !url.is_empty() => url,
+        _ => return,
+    };
+    static CLIENT: OnceLock<reqwest::Client> = OnceLock::new();
+    let client = CLIENT.get_or_init(|| {
+        reqwest::Client::builder()
+            .timeout(WEBHOOK_TIMEOUT)
+            .build()
+            .expect("failed to build the alert webhook client")
+    });
+    let result = client.post(&url).json(&json!({ "text": message })).send().await.and_then(|r| r.error_for_status());
+    if let Err(e) = result {
+        eprintln!("Failed to deliver alert to webhook: {}", e);
+    }
+}
@@ -0,0 +1,396 @@
+use axum::extract::{FromRef, Path, Query, State};
+use axum::http::StatusCode;
+use axum::response::sse::{Event, KeepAlive, Sse};
+use axum::response::{IntoResponse, Response};
+use axum::routing::get;
+use axum::{Json, Router};
+use chrono::{DateTime, Utc};
+use ethers::types::{Address, H256, U256};
+use futures_util::stream::{self, Stream, StreamExt};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sqlx::PgPool;
+use std::str::FromStr;
+use std::sync::Arc;
+use tokio::net::TcpListener;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::{broadcast, mpsc};
+use uuid::Uuid;
+use crate::db::{self, Deposit};
+use crate::shutdown::Shutdown;
+
+const DEFAULT_PAGE_SIZE: i64 = 50;
+const MAX_PAGE_SIZE: i64 = 500;
+
+// Changed deposits buffered per stream subscriber before it starts missing them
+const FEED_CAPACITY: usize = 1024;
+
+#[derive(Clone)]
+struct ApiState {
+    pool: PgPool,
+    // Current state of every deposit reported changed through `Updates`
+    feed: broadcast::Sender<Arc<Deposit>>,
+    shutdown: Shutdown,
+}
+
+impl FromRef<ApiState> for PgPool {
+    fn from_ref(state: &ApiState) -> Self {
+        state.pool.clone()
+    }
+}
+
+// Read-only HTTP API over the deposits table, for the frontend and support.
+// Addresses are EIP-55 checksummed and amounts are decimal strings in base units.
+pub async fn serve(listener: TcpListener, pool: PgPool, changes: mpsc::UnboundedReceiver<Uuid>, mut shutdown: Shutdown) {
+    let (feed, _) = broadcast::channel(FEED_CAPACITY);
+    tokio::spawn(publish(pool.clone(), changes, feed.clone()));
+
+    let state = ApiState { pool, feed, shutdown: shutdown.clone() };
+    let app = Router::new()
+        .route("/deposits", get(list_deposits))
+        .route("/deposits/tx/:tx_hash", get(deposits_by_tx_hash))
+        .route("/deposits/nonce/:chain_id/:nonce", get(deposits_by_nonce))
+        .route("/deposits/awaiting-approval", get(awaiting_approval))
+        .route("/deposits/stream", get(stream_deposits))
+        .route("/fees", get(fee_totals))
+        .with_state(state);
+    if let Ok(addr) = listener.local_addr() {
+        println!("API listening on {}", addr);
+    }
+    let result = axum::serve(listener, app)
+        .with_graceful_shutdown(async move { shutdown.requested().await })
+        .await;
+    if let Err(e) = result {
+        eprintln!("API server failed: {}", e);
+    }
+    println!("API server stopped");
+}
+
+enum ApiError {
+    BadRequest(String),
+    NotFound(String),
+    Database(sqlx::Error),
+}
+
+impl From<sqlx::Error> for ApiError {
+    fn from(e: sqlx::Error) -> Self {
+        ApiError::Database(e)
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let (status, message) = match self {
+            ApiError::BadRequest(message) => (StatusCode::BAD_REQUEST, message),
+            ApiError::NotFound(message) => (StatusCode::NOT_FOUND, message),
+            ApiError::Database(e) => {
+                eprintln!("API database error: {}", e);
+                (StatusCode::INTERNAL_SERVER_ERROR, "database error".to_string())
+            }
+        };
+        (status, Json(json!({ "error": message }))).into_response()
+    }
+}
+
+type ApiResult<T> = Result<Json<T>, ApiError>;
+
+fn timestamp(value: Option<DateTime<Utc>>) -> Option<String> {
+    value.map(|value| value.to_rfc3339())
+}
+
+// A deposit and where it is in its lifecycle
+#[derive(Serialize)]
+struct DepositView {
+    deposit_id: String,
+    chain_id: String,
+    transaction_hash: String,
+    log_index: Option<i64>,
+    block_number: i64,
+    block_hash: Option<String>,
+    block_timestamp: Option<String>,
+    bridge_address: Option<String>,
+    token_address: String,
+    from_address: String,
+    to_address: String,
+    amount: String,
+    nonce: String,
+    // indexed, finalized, distributing or distributed
+    stage: &'static str,
+    status: String,
+    status_reason: Option<String>,
+    indexed_at: Option<String>,
+    finality_confirmed: bool,
+    distribution_id: Option<String>,
+    distribution_tx_hash: Option<String>,
+    distribution_amount: Option<String>,
+    dust: Option<String>,
+    distribution_attempts: i32,
+    revert_reason: Option<String>,
+    distributed: bool,
+    updated_at: Option<String>,
+}
+
+impl From<&Deposit> for DepositView {
+    fn from(deposit: &Deposit) -> Self {
+        DepositView {
+            deposit_id: deposit.deposit_id.to_string(),
+            chain_id: deposit.chain_id.clone(),
+            transaction_hash: deposit.transaction_hash.to_string(),
+            log_index: deposit.log_index,
+            block_number: deposit.block_number,
+            block_hash: deposit.block_hash.map(|hash| hash.to_string()),
+            block_timestamp: timestamp(deposit.block_timestamp),
+            bridge_address: deposit.bridge_address.map(|address| address.to_string()),
+            token_address: deposit.token_address.to_string(),
+            from_address: deposit.from_address.to_string(),
+            to_address: deposit.to_address.to_string(),
+            amount: deposit.amount.to_string(),
+            nonce: deposit.nonce.to_string(),
+            stage: deposit.stage(),
+            status: deposit.status.clone(),
+            status_reason: deposit.status_reason.clone(),
+            indexed_at: timestamp(deposit.created_at),
+            finality_confirmed: deposit.finality_confirmed == Some(true),
+            distribution_id: deposit.distribution_id.map(|id| id.to_string()),
+            distribution_tx_hash: deposit.distribution_tx_hash.map(|hash| hash.to_string()),
+            distribution_amount: deposit.distribution_amount.as_ref().map(|amount| amount.to_string()),
+            dust: deposit.dust.as_ref().map(|dust| dust.to_string()),
+            distribution_attempts: deposit.distribution_attempts,
+            revert_reason: deposit.revert_reason.clone(),
+            distributed: deposit.processed == Some(true),
+            updated_at: timestamp(deposit.updated_at),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct DistributionTransactionView {
+    tx_hash: String,
+    chain_id: String,
+    account_nonce: i64,
+    max_fee_per_gas: String,
+    max_priority_fee_per_gas: String,
+    replaces_tx_hash: Option<String>,
+}
+
+#[derive(Serialize)]
+struct ApprovalView {
+    approver: String,
+    signature: String,
+    created_at: Option<String>,
+}
+
+// A deposit with every distribute tx sent for it and the operator approvals it received
+#[derive(Serialize)]
+struct DepositDetails {
+    #[serde(flatten)]
+    deposit: DepositView,
+    distribution_transactions: Vec<DistributionTransactionView>,
+    approvals: Vec<ApprovalView>,
+}
+
+async fn details(pool: &PgPool, deposits: Vec<Deposit>) -> Result<Vec<DepositDetails>, ApiError> {
+    let mut details = Vec::with_capacity(deposits.len());
+    for deposit in &deposits {
+        let distribution_transactions = db::get_distribution_transactions(pool, deposit.deposit_id).await?
+            .into_iter()
+            .map(|tx| DistributionTransactionView {
+                tx_hash: tx.tx_hash.to_string(),
+                chain_id: tx.chain_id,
+                account_nonce: tx.account_nonce,
+                max_fee_per_gas: tx.max_fee_per_gas,
+                max_priority_fee_per_gas: tx.max_priority_fee_per_gas,
+                replaces_tx_hash: tx.replaces_tx_hash.map(|hash| hash.to_string()),
+            })
+            .collect();
+        let approvals = db::get_deposit_approvals(pool, deposit.deposit_id).await?
+            .into_iter()
+            .map(|approval| ApprovalView {
+                approver: approval.approver.to_string(),
+                signature: approval.signature,
+                created_at: timestamp(approval.created_at),
+            })
+            .collect();
+        details.push(DepositDetails { deposit: deposit.into(), distribution_transactions, approvals });
+    }
+    Ok(details)
+}
+
+// GET /deposits/tx/{tx_hash}: every deposit emitted by a source transaction
+async fn deposits_by_tx_hash(State(pool): State<PgPool>, Path(tx_hash): Path<String>) -> ApiResult<Vec<DepositDetails>> {
+    let hash = H256::from_str(&tx_hash).map_err(|_| ApiError::BadRequest(format!("invalid transaction hash {}", tx_hash)))?;
+    let deposits = db::get_deposits_by_tx_hash(&pool, hash).await?;
+    if deposits.is_empty() {
+        return Err(ApiError::NotFound(format!("no deposit indexed for transaction {:?}", hash)));
+    }
+    Ok(Json(details(&pool, deposits).await?))
+}
+
+// GET /deposits/nonce/{chain_id}/{nonce}: the deposit with a source chain nonce
+async fn deposits_by_nonce(
+    State(pool): State<PgPool>,
+    Path((chain_id, nonce)): Path<(String, String)>,
+) -> ApiResult<Vec<DepositDetails>> {
+    let value = U256::from_dec_str(&nonce).map_err(|_| ApiError::BadRequest(format!("invalid nonce {}", nonce)))?;
+    let deposits = db::get_deposits_by_nonce(&pool, &chain_id, value).await?;
+    if deposits.is_empty() {
+        return Err(ApiError::NotFound(format!("no deposit indexed on {} with nonce {}", chain_id, nonce)));
+    }
+    Ok(Json(details(&pool, deposits).await?))
+}
+
+#[derive(Deserialize)]
+struct ListParams {
+    sender: Option<String>,
+    recipient: Option<String>,
+    limit: Option<i64>,
+    offset: Option<i64>,
+}
+
+#[derive(Serialize)]
+struct DepositPage {
+    deposits: Vec<DepositView>,
+    limit: i64,
+    offset: i64,
+    // Offset of the next page, or null on the last one
+    next_offset: Option<i64>,
+}
+
+fn parse_address(name: &str, value: Option<&str>) -> Result<Option<Address>, ApiError> {
+    value
+        .map(|value| Address::from_str(value).map_err(|_| ApiError::BadRequest(format!("invalid {} address {}", name, value))))
+        .transpose()
+}
+
+// GET /deposits?sender=0x..&recipient=0x..&limit=50&offset=0: newest first; at least one of
+// sender and recipient is required, and both narrow the results
+async fn list_deposits(State(pool): State<PgPool>, Query(params): Query<ListParams>) -> ApiResult<DepositPage> {
+    let sender = parse_address("sender", params.sender.as_deref())?;
+    let recipient = parse_address("recipient", params.recipient.as_deref())?;
+    if sender.is_none() && recipient.is_none() {
+        return Err(ApiError::BadRequest("sender or recipient is required".to_string()));
+    }
+    let limit = params.limit.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE);
+    let offset = params.offset.unwrap_or(0).max(0);
+
+    // One extra row tells whether there is a next page
+    let mut deposits = db::get_deposits_by_party(&pool, sender, recipient, limit + 1, offset).await?;
+    let next_offset = (deposits.len() as i64 > limit).then_some(offset + limit);
+    deposits.truncate(limit as usize);
+    Ok(Json(DepositPage { deposits: deposits.iter().map(DepositView::from).collect(), limit, offset, next_offset }))
+}
+
+// GET /deposits/awaiting-approval: deposits above an approval threshold and the approvals so far
+async fn awaiting_approval(State(pool): State<PgPool>) -> ApiResult<Vec<DepositDetails>> {
+    let deposits = db::get_deposits_by_status(&pool, "awaiting_approval").await?;
+    Ok(Json(details(&pool, deposits).await?))
+}
+
+#[derive(Serialize)]
+struct FeeTotalView {
+    chain_id: String,
+    token_address: String,
+    deposits: i64,
+    total: String,
+}
+
+// GET /fees: fees collected per destination token
+async fn fee_totals(State(pool): State<PgPool>) -> ApiResult<Vec<FeeTotalView>> {
+    let totals = db::get_fee_totals(&pool).await?
+        .into_iter()
+        .map(|total| FeeTotalView {
+            chain_id: total.chain_id,
+            token_address: total.token_address.to_string(),
+            deposits: total.deposits,
+            total: total.total.to_string(),
+        })
+        .collect();
+    Ok(Json(totals))
+}
+
+// Load each deposit reported changed and hand its current state to the stream subscribers
+async fn publish(pool: PgPool, mut changes: mpsc::UnboundedReceiver<Uuid>, feed: broadcast::Sender<Arc<Deposit>>) {
+    while let Some(deposit_id) = changes.recv().await {
+        if feed.receiver_count() == 0 {
+            continue;
+        }
+        match db::get_deposit(&pool, deposit_id).await {
+            Ok(Some(deposit)) => {
+                let _ = feed.send(Arc::new(deposit));
+            }
+            Ok(None) => {}
+            Err(e) => eprintln!("Failed to load changed deposit {} for streaming: {}", deposit_id, e),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct StreamParams {
+    tx_hash: Option<String>,
+    address: Option<String>,
+}
+
+// Deposits a stream subscriber follows
+#[derive(Clone, Copy)]
+enum Subscription {
+    Transaction(H256),
+    // Deposits from or to the address
+    Address(Address),
+}
+
+impl Subscription {
+    fn matches(&self, deposit: &Deposit) -> bool {
+        match self {
+            Subscription::Transaction(hash) => deposit.transaction_hash.0 == *hash,
+            Subscription::Address(address) => deposit.from_address.0 == *address || deposit.to_address.0 == *address,
+        }
+    }
+}
+
+fn deposit_event(deposit: &Deposit) -> Result<Event, axum::Error> {
+    Event::default().event("deposit").id(deposit.deposit_id.to_string()).json_data(DepositView::from(deposit))
+}
+
+// GET /deposits/stream?tx_hash=0x.. or ?address=0x..: server-sent events with the deposit's
+// state each time it changes (indexed, finalized, distributing, distributed, held, failed...).
+// A transaction subscription starts with the current state of its deposits, if already indexed.
+async fn stream_deposits(
+    State(state): State<ApiState>,
+    Query(params): Query<StreamParams>,
+) -> Result<Sse<impl Stream<Item = Result<Event, axum::Error>>>, ApiError> {
+    let subscription = match (params.tx_hash.as_deref(), params.address.as_deref()) {
+        (Some(tx_hash), None) => Subscription::Transaction(
+            H256::from_str(tx_hash).map_err(|_| ApiError::BadRequest(format!("invalid transaction hash {}", tx_hash)))?,
+        ),
+        (None, Some(address)) => Subscription::Address(
+            Address::from_str(address).map_err(|_| ApiError::BadRequest(format!("invalid address {}", address)))?,
+        ),
+        _ => return Err(ApiError::BadRequest("exactly one of tx_hash and address is required".to_string())),
+    };
+
+    // Subscribe before reading the current state so that no change falls in between
+    let receiver = state.feed.subscribe();
+    let current = match subscription {
+        Subscription::Transaction(hash) => db::get_deposits_by_tx_hash(&state.pool, hash).await?,
+        Subscription::Address(_) => Vec::new(),
+    };
+    let current: Vec<_> = current.iter().map(deposit_event).collect();
+
+    // Ends on shutdown so that open streams do not hold up the server
+    let changes = stream::unfold((receiver, state.shutdown), move |(mut receiver, mut shutdown)| async move {
+        loop {
+            let event = tokio::select! {
+                received = receiver.recv() => match received {
+                    Ok(deposit) if subscription.matches(&deposit) => deposit_event(&deposit),
+                    Ok(_) => continue,
+                    // The subscriber fell behind; tell it to fetch the current state again
+                    Err(RecvError::Lagged(missed)) => Ok(Event::default().event("lagged").data(missed.to_string())),
+                    Err(RecvError::Closed) => return None,
+                },
+                _ = shutdown.requested() => return None,
+            };
+            return Some((event, (receiver, shutdown)));
+        }
+    });
+    Ok(Sse::new(stream::iter(current).chain(changes)).keep_alive(KeepAlive::default()))
+}
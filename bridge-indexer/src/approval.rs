@@ -0,0 +1,69 @@
+use ethers::prelude::*;
+use sqlx::PgPool;
+use std::env;
+use std::str::FromStr;
+use crate::db::{self, Deposit};
+
+// Who may approve large deposits, from the environment:
+// - APPROVERS: comma-separated operator addresses
+// - REQUIRED_APPROVALS: distinct approvers needed before a deposit is distributed (default 1)
+// An approval is the operator's signature over `message(deposit)`, so it authenticates the
+// operator and cannot be moved to another deposit or survive an edit of this one.
+#[derive(Debug, Clone, Default)]
+pub struct ApprovalPolicy {
+    approvers: Vec<Address>,
+    required: usize,
+}
+
+impl ApprovalPolicy {
+    pub fn from_env() -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
+        let approvers = env::var("APPROVERS")
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|approver| !approver.is_empty())
+            .map(Address::from_str)
+            .collect::<Result<Vec<_>, _>>()?;
+        let required: usize = env::var("REQUIRED_APPROVALS").unwrap_or_else(|_| "1".to_string()).parse()?;
+        if required == 0 {
+            return Err("REQUIRED_APPROVALS must be at least 1".into());
+        }
+        Ok(Self { approvers, required })
+    }
+
+    pub fn required(&self) -> usize {
+        self.required
+    }
+
+    pub fn has_approvers(&self) -> bool {
+        self.approvers.len() >= self.required
+    }
+
+    pub fn is_approver(&self, address: Address) -> bool {
+        self.approvers.contains(&address)
+    }
+
+    // Approvals recorded for a deposit whose signature is valid and from a current approver
+    pub async fn valid_approvals(&self, pool: &PgPool, deposit: &Deposit) -> Result<usize, sqlx::Error> {
+        let message = message(deposit);
+        let approvals = db::get_deposit_approvals(pool, deposit.deposit_id).await?;
+        Ok(approvals.iter()
+            .filter(|approval| {
+                let Ok(signature) = Signature::from_str(&approval.signature) else {
+                    return false;
+                };
+                let approver = approval.approver.0;
+                self.is_approver(approver) && signature.verify(message.as_str(), approver).is_ok()
+            })
+            .count())
+    }
+}
+
+// The text an operator signs (EIP-191) to approve a deposit. Addresses stay lowercase
+// hex so that signatures collected before checksummed rendering remain valid.
+pub fn message(deposit: &Deposit) -> String {
+    format!(
+        "Approve bridge deposit {} from {} (nonce {}): {} of token {:?} to {:?}",
+        deposit.deposit_id, deposit.chain_id, deposit.nonce, deposit.amount, deposit.token_address.0, deposit.to_address.0
+    )
+}
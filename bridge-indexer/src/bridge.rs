@@ -0,0 +1,139 @@
+use chrono::{DateTime, Utc};
+use ethers::abi::{encode, Token};
+use ethers::prelude::*;
+use ethers::utils::keccak256;
+use crate::abi::{TokenBridge, TokenBridgeErrors};
+
+// Blocks per eth_getLogs request when scanning event history
+pub const LOG_CHUNK_BLOCKS: u64 = 10_000;
+
+// Revert string of the `whenNotPaused` modifier in TokenBridge.sol
+pub const PAUSED_REVERT: &str = "Bridge: paused";
+
+// Read the paused state of a bridge contract.
+// TokenBridge keeps `_paused` private and exposes no getter, so we simulate a
+// zero-amount `deposit` with eth_call: `whenNotPaused` runs before any other
+// check, so a paused bridge reverts with "Bridge: paused" and an active one
+// reverts with a different reason (or not at all).
+pub async fn is_paused<M: Middleware + 'static>(contract: &TokenBridge<M>) -> Result<bool, ContractError<M>> {
+    match contract.deposit(H160::zero(), U256::zero(), H160::zero()).call().await {
+        Ok(()) => Ok(false),
+        Err(e) => match e.decode_revert::<String>() {
+            Some(reason) => Ok(reason == PAUSED_REVERT),
+            None => Err(e),
+        },
+    }
+}
+
+// Human-readable reason of a reverted call: the `Error(string)` message (e.g.
+// "Bridge: token not supported"), a TokenBridge custom error, or the node's
+// "execution reverted" message when it returns no revert data. None if the call
+// failed for another reason (network, RPC errors).
+pub fn revert_reason<M: Middleware>(e: &ContractError<M>) -> Option<String> {
+    if let Some(reason) = e.decode_revert::<String>() {
+        return Some(reason);
+    }
+    if let Some(error) = e.decode_contract_revert::<TokenBridgeErrors>() {
+        return Some(format!("{:?}", error));
+    }
+    if let Some(data) = e.as_revert() {
+        return Some(format!("reverted with data {}", data));
+    }
+    let message = e.to_string();
+    message.contains("execution reverted").then_some(message)
+}
+
+// Reason encoded in raw revert data, e.g. the output of a call trace: an `Error(string)`
+// message or a TokenBridge custom error. None if the data matches neither.
+pub fn decode_revert_data(data: &[u8]) -> Option<String> {
+    if let Some(reason) = String::decode_with_selector(data) {
+        return Some(reason);
+    }
+    TokenBridgeErrors::decode_with_selector(data).map(|error| format!("{:?}", error))
+}
+
+// Revert of `distribute` when the distribution id was already paid out, by this or an
+// earlier distribution; the deposit is done rather than failed
+pub const ALREADY_PROCESSED_REVERT: &str = "Bridge: deposit already processed";
+
+pub fn is_already_processed(reason: &str) -> bool {
+    reason.contains(ALREADY_PROCESSED_REVERT)
+}
+
+// Reverts that will not go away by retrying the same distribution. Anything else
+// (paused bridge, wrong distributor key, low bridge balance, out of gas) may succeed
+// later and is retried up to MAX_DISTRIBUTION_ATTEMPTS times.
+const PERMANENT_REVERTS: &[&str] = &["Bridge: token not supported"];
+
+pub fn is_permanent_revert(reason: &str) -> bool {
+    PERMANENT_REVERTS.iter().any(|permanent| reason.contains(permanent))
+}
+
+// Timestamp of the block a log was emitted in. None if the block cannot be fetched
+// (e.g. it was reorged out since); the deposit is still indexed without it.
+pub async fn block_timestamp<M: Middleware>(client: &M, block_hash: H256) -> Option<DateTime<Utc>> {
+    match client.get_block(block_hash).await {
+        Ok(Some(block)) => DateTime::from_timestamp(block.timestamp.as_u64() as i64, 0),
+        Ok(None) => None,
+        Err(e) => {
+            eprintln!("Failed to fetch block {:?}: {}", block_hash, e);
+            None
+        }
+    }
+}
+
+// Id passed to `distribute` as `depositNonce`: keccak256(abi.encode(sourceChainId, sourceBridge, nonce)).
+// TokenBridge keys processedDeposits by this value alone, so passing the raw source nonce
+// would let nonce 5 from one source chain block nonce 5 from another.
+pub fn distribution_id(source_chain_id: u64, source_bridge: Address, nonce: U256) -> U256 {
+    let encoded = encode(&[Token::Uint(source_chain_id.into()), Token::Address(source_bridge), Token::Uint(nonce)]);
+    U256::from_big_endian(&keccak256(encoded))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    // Holesky bridge; expected ids computed independently as keccak256(abi.encode(17000, bridge, nonce))
+    const HOLESKY_BRIDGE: &str = "0xc2d3fF175A41B78d6b3897A778809973bF2978C9";
+
+    fn id(hex: &str) -> U256 {
+        U256::from_big_endian(H256::from_str(hex).unwrap().as_bytes())
+    }
+
+    #[test]
+    fn distribution_id_matches_abi_encode_hash() {
+        let bridge = Address::from_str(HOLESKY_BRIDGE).unwrap();
+        assert_eq!(
+            distribution_id(17000, bridge, U256::zero()),
+            id("0x85e275d68a7ee9349e5fceeb0f3ee9e04f5d243f82c8dc0007d728f575eb9c0d")
+        );
+        assert_eq!(
+            distribution_id(17000, bridge, U256::from(5)),
+            id("0x65446fc32190d64e6b75be726b904cc5fde824462e7fa45add7b6b815e5a8f2a")
+        );
+    }
+
+    #[test]
+    fn decodes_revert_strings_from_raw_data() {
+        // Error(string) selector followed by the ABI-encoded message
+        let mut data = vec![0x08, 0xc3, 0x79, 0xa0];
+        data.extend(encode(&[Token::String(ALREADY_PROCESSED_REVERT.to_string())]));
+        let reason = decode_revert_data(&data).unwrap();
+        assert_eq!(reason, "Bridge: deposit already processed");
+        assert!(is_already_processed(&reason));
+        assert!(!is_permanent_revert(&reason));
+        assert_eq!(decode_revert_data(&[]), None);
+        assert_eq!(decode_revert_data(&[0xde, 0xad, 0xbe, 0xef]), None);
+    }
+
+    #[test]
+    fn distribution_id_differs_per_source() {
+        let bridge = Address::from_str(HOLESKY_BRIDGE).unwrap();
+        let nonce = U256::from(5);
+        assert_ne!(distribution_id(17000, bridge, nonce), distribution_id(84532, bridge, nonce));
+        assert_ne!(distribution_id(17000, bridge, nonce), distribution_id(17000, Address::zero(), nonce));
+        assert_ne!(distribution_id(17000, bridge, nonce), nonce);
+    }
+}
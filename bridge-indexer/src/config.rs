@@ -0,0 +1,28 @@
+use ethers::types::U256;
+use std::env;
+
+// Per-chain setting: `{PREFIX}_{NAME}` (e.g. HOLESKY_PRIVATE_KEY) takes precedence over the shared `{NAME}`
+pub fn chain_var(prefix: &str, name: &str) -> Option<String> {
+    env::var(format!("{}_{}", prefix, name)).or_else(|_| env::var(name)).ok()
+}
+
+pub fn required_chain_var(prefix: &str, name: &str) -> String {
+    chain_var(prefix, name).unwrap_or_else(|| panic!("{}_{} or {} must be set", prefix, name, name))
+}
+
+// Setting of one role only, with no shared fallback (e.g. OPERATOR_PRIVATE_KEY)
+pub fn prefixed_var(prefix: &str, name: &str) -> Option<String> {
+    env::var(format!("{}_{}", prefix, name)).ok()
+}
+
+pub fn required_prefixed_var(prefix: &str, name: &str) -> String {
+    prefixed_var(prefix, name).unwrap_or_else(|| panic!("{}_{} must be set", prefix, name))
+}
+
+// Token amount in base units, e.g. a limit or threshold; None when unset
+pub fn amount_var(prefix: &str, name: &str) -> Result<Option<U256>, Box<dyn std::error::Error + Send + Sync>> {
+    match chain_var(prefix, name) {
+        Some(value) => Ok(Some(U256::from_dec_str(value.trim())?)),
+        None => Ok(None),
+    }
+}
@@ -1,25 +1,211 @@
 use chrono::{DateTime, Utc};
-use sqlx::{Error, PgPool, FromRow};
+use sqlx::encode::IsNull;
+use sqlx::error::BoxDynError;
+use sqlx::postgres::{PgArgumentBuffer, PgTypeInfo, PgValueRef, Postgres};
+use sqlx::types::BigDecimal;
+use sqlx::{Error, PgConnection, PgPool, FromRow};
 use uuid::Uuid;
-use ethers::types::U256;
+use ethers::contract::LogMeta;
+use ethers::types::{H160, H256, U256};
+use ethers::utils::to_checksum;
+use std::fmt;
+use std::str::FromStr;
+use crate::abi::DepositFilter;
+
+// Amounts and nonces are stored as NUMERIC(78,0), which holds every U256 exactly
+pub fn to_numeric(value: U256) -> BigDecimal {
+    BigDecimal::from_str(&value.to_string()).expect("a U256 is a valid decimal")
+}
+
+// None for values with no U256 equivalent: fractional, negative or above U256::MAX
+pub fn from_numeric(value: &BigDecimal) -> Option<U256> {
+    if !value.is_integer() {
+        return None;
+    }
+    U256::from_dec_str(&value.with_scale(0).to_string()).ok()
+}
+
+// Addresses and hashes are stored as raw bytes: BYTEA columns of 20 and 32 bytes
+macro_rules! bytea_type {
+    ($name:ident, $inner:ty) => {
+        #[derive(Clone, Copy, PartialEq, Eq, Hash)]
+        pub struct $name(pub $inner);
+
+        impl fmt::Debug for $name {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                fmt::Display::fmt(self, f)
+            }
+        }
+
+        impl From<$inner> for $name {
+            fn from(value: $inner) -> Self {
+                $name(value)
+            }
+        }
+
+        impl sqlx::Type<Postgres> for $name {
+            fn type_info() -> PgTypeInfo {
+                <&[u8] as sqlx::Type<Postgres>>::type_info()
+            }
+        }
+
+        impl sqlx::Encode<'_, Postgres> for $name {
+            fn encode_by_ref(&self, buf: &mut PgArgumentBuffer) -> IsNull {
+                <&[u8] as sqlx::Encode<Postgres>>::encode(self.0.as_bytes(), buf)
+            }
+        }
+
+        impl<'r> sqlx::Decode<'r, Postgres> for $name {
+            fn decode(value: PgValueRef<'r>) -> Result<Self, BoxDynError> {
+                let bytes = <&[u8] as sqlx::Decode<Postgres>>::decode(value)?;
+                if bytes.len() != <$inner>::len_bytes() {
+                    return Err(format!("expected {} bytes for {}, got {}", <$inner>::len_bytes(), stringify!($name), bytes.len()).into());
+                }
+                Ok($name(<$inner>::from_slice(bytes)))
+            }
+        }
+    };
+}
+
+bytea_type!(DbAddress, H160);
+bytea_type!(DbHash, H256);
+
+// Addresses are shown EIP-55 checksummed in logs and the CLI
+impl fmt::Display for DbAddress {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&to_checksum(&self.0, None))
+    }
+}
+
+impl fmt::Display for DbHash {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}", self.0)
+    }
+}
 
 #[derive(Debug, Clone, FromRow)]
 pub struct Deposit {
     pub deposit_id: Uuid,
     pub chain_id: String,
-    pub transaction_hash: String,
+    pub transaction_hash: DbHash,
     pub block_number: i64,
-    pub token_address: String,
-    pub from_address: String,
-    pub to_address: String,
-    pub amount: String,
-    pub nonce: String,
+    pub block_hash: Option<DbHash>,
+    pub block_timestamp: Option<DateTime<Utc>>,
+    pub tx_index: Option<i64>,
+    pub log_index: Option<i64>,
+    pub bridge_address: Option<DbAddress>,
+    pub token_address: DbAddress,
+    pub from_address: DbAddress,
+    pub to_address: DbAddress,
+    pub amount: BigDecimal,
+    pub nonce: BigDecimal,
     pub processed: Option<bool>,
     pub finality_confirmed: Option<bool>,
+    pub distribution_tx_hash: Option<DbHash>,
+    pub revert_reason: Option<String>,
+    pub distribution_attempts: i32,
+    pub status: String,
+    pub status_reason: Option<String>,
+    pub limits_waived: bool,
+    pub distribution_id: Option<DbHash>,
+    pub distribution_amount: Option<BigDecimal>,
+    pub dust: Option<BigDecimal>,
+    pub legacy_nonce: bool,
     pub created_at: Option<DateTime<Utc>>,
     pub updated_at: Option<DateTime<Utc>>,
 }
 
+impl Deposit {
+    // A newly indexed deposit from a decoded Deposit event
+    pub fn from_event(chain_id: &str, event: &DepositFilter, meta: &LogMeta, block_timestamp: Option<DateTime<Utc>>) -> Self {
+        Deposit {
+            deposit_id: Uuid::new_v4(),
+            chain_id: chain_id.to_string(),
+            transaction_hash: DbHash(meta.transaction_hash),
+            block_number: meta.block_number.as_u64() as i64,
+            block_hash: Some(DbHash(meta.block_hash)),
+            block_timestamp,
+            tx_index: Some(meta.transaction_index.as_u64() as i64),
+            log_index: Some(meta.log_index.as_u64() as i64),
+            bridge_address: Some(DbAddress(meta.address)),
+            token_address: DbAddress(event.token),
+            from_address: DbAddress(event.from),
+            to_address: DbAddress(event.to),
+            amount: to_numeric(event.amount),
+            nonce: to_numeric(event.nonce),
+            processed: Some(false),
+            finality_confirmed: Some(false),
+            distribution_tx_hash: None,
+            revert_reason: None,
+            distribution_attempts: 0,
+            status: "pending".to_string(),
+            status_reason: None,
+            limits_waived: false,
+            distribution_id: None,
+            distribution_amount: None,
+            dust: None,
+            legacy_nonce: false,
+            created_at: None,
+            updated_at: None,
+        }
+    }
+
+    // Where the deposit is in the bridge lifecycle: indexed, finalized, distributing
+    // (a distribute tx was sent) or distributed. `status` says whether it is held or blocked.
+    pub fn stage(&self) -> &'static str {
+        if self.processed == Some(true) {
+            "distributed"
+        } else if self.distribution_tx_hash.is_some() {
+            "distributing"
+        } else if self.finality_confirmed == Some(true) {
+            "finalized"
+        } else {
+            "indexed"
+        }
+    }
+}
+
+// One broadcast distribute tx; fee-bumped replacements link to the tx they replace
+#[derive(Debug, Clone, FromRow)]
+pub struct DistributionTransaction {
+    pub tx_hash: DbHash,
+    pub deposit_id: Uuid,
+    pub chain_id: String,
+    pub account_nonce: i64,
+    pub max_fee_per_gas: String,
+    pub max_priority_fee_per_gas: String,
+    pub replaces_tx_hash: Option<DbHash>,
+}
+
+// An operator's signed approval of a deposit above its route's approval threshold
+#[derive(Debug, Clone, FromRow)]
+pub struct DepositApproval {
+    pub deposit_id: Uuid,
+    pub approver: DbAddress,
+    pub signature: String,
+    pub created_at: Option<DateTime<Utc>>,
+}
+
+// Bridge fee kept from a deposit, in destination token units
+#[derive(Debug, Clone)]
+pub struct Fee {
+    pub deposit_id: Uuid,
+    pub chain_id: String,
+    pub token_address: DbAddress,
+    pub fee_model: String,
+    pub amount: U256,
+    pub gross_amount: U256,
+}
+
+// Fees collected on distributed deposits for one destination token
+#[derive(Debug, Clone, FromRow)]
+pub struct FeeTotal {
+    pub chain_id: String,
+    pub token_address: DbAddress,
+    pub deposits: i64,
+    pub total: BigDecimal,
+}
+
 #[allow(dead_code)]
 #[derive(Debug, Clone)]
 pub struct LastProcessedBlock {
@@ -38,18 +224,174 @@ pub async fn init_db(pool: &PgPool) -> Result<(), Error> {
         CREATE TABLE IF NOT EXISTS deposits (
             deposit_id UUID PRIMARY KEY,
             chain_id TEXT NOT NULL,
-            transaction_hash TEXT NOT NULL,
+            transaction_hash BYTEA NOT NULL,
             block_number BIGINT NOT NULL,
-            token_address TEXT NOT NULL,
-            from_address TEXT NOT NULL,
-            to_address TEXT NOT NULL,
-            amount TEXT NOT NULL,
-            nonce TEXT NOT NULL,
+            token_address BYTEA NOT NULL,
+            from_address BYTEA NOT NULL,
+            to_address BYTEA NOT NULL,
+            amount NUMERIC(78,0) NOT NULL,
+            nonce NUMERIC(78,0) NOT NULL,
             processed BOOLEAN DEFAULT FALSE,
             finality_confirmed BOOLEAN DEFAULT FALSE,
             created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
-            updated_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
-            CONSTRAINT unique_nonce_chain UNIQUE (nonce, chain_id)
+            updated_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP
+        )
+        "#,
+    )
+    .execute(pool)
+    .await?;
+
+    // A deposit is one Deposit log: deduplicate on (chain, tx hash, log index), not on the tx hash
+    // (a tx can emit several deposits) or the nonce (a reorg can move a nonce to another tx;
+    // such collisions are caught before distribution instead)
+    sqlx::query("ALTER TABLE deposits ADD COLUMN IF NOT EXISTS log_index BIGINT")
+        .execute(pool)
+        .await?;
+    sqlx::query("ALTER TABLE deposits DROP CONSTRAINT IF EXISTS unique_nonce_chain")
+        .execute(pool)
+        .await?;
+    sqlx::query("ALTER TABLE deposits DROP CONSTRAINT IF EXISTS deposits_transaction_hash_key")
+        .execute(pool)
+        .await?;
+    sqlx::query(
+        "CREATE UNIQUE INDEX IF NOT EXISTS idx_deposits_chain_tx_log ON deposits(chain_id, transaction_hash, log_index)",
+    )
+    .execute(pool)
+    .await?;
+
+    // Where the Deposit log sits on chain: block hash to detect reorgs, block timestamp
+    // to order deposits by on-chain time, and the bridge contract that emitted it
+    sqlx::query("ALTER TABLE deposits ADD COLUMN IF NOT EXISTS block_hash BYTEA")
+        .execute(pool)
+        .await?;
+    sqlx::query("ALTER TABLE deposits ADD COLUMN IF NOT EXISTS block_timestamp TIMESTAMP WITH TIME ZONE")
+        .execute(pool)
+        .await?;
+    sqlx::query("ALTER TABLE deposits ADD COLUMN IF NOT EXISTS tx_index BIGINT")
+        .execute(pool)
+        .await?;
+    sqlx::query("ALTER TABLE deposits ADD COLUMN IF NOT EXISTS bridge_address BYTEA")
+        .execute(pool)
+        .await?;
+
+    // Hash of the last distribute tx sent for a deposit, persisted before awaiting its receipt
+    sqlx::query("ALTER TABLE deposits ADD COLUMN IF NOT EXISTS distribution_tx_hash BYTEA")
+        .execute(pool)
+        .await?;
+
+    // Why the last distribution attempt reverted, in simulation or on-chain
+    sqlx::query("ALTER TABLE deposits ADD COLUMN IF NOT EXISTS revert_reason TEXT")
+        .execute(pool)
+        .await?;
+
+    // Reverted distribute txs so far, and 'pending' until a deposit fails permanently ('failed')
+    sqlx::query("ALTER TABLE deposits ADD COLUMN IF NOT EXISTS distribution_attempts INTEGER NOT NULL DEFAULT 0")
+        .execute(pool)
+        .await?;
+    sqlx::query("ALTER TABLE deposits ADD COLUMN IF NOT EXISTS status TEXT NOT NULL DEFAULT 'pending'")
+        .execute(pool)
+        .await?;
+
+    // Why a deposit is held ('held_for_review', 'awaiting_approval', 'blocked', 'rejected'), and whether an operator released it past the rate limits
+    sqlx::query("ALTER TABLE deposits ADD COLUMN IF NOT EXISTS status_reason TEXT")
+        .execute(pool)
+        .await?;
+    sqlx::query("ALTER TABLE deposits ADD COLUMN IF NOT EXISTS limits_waived BOOLEAN NOT NULL DEFAULT FALSE")
+        .execute(pool)
+        .await?;
+
+    // Collision-free id passed to `distribute` in place of the source nonce; unique across deposits
+    sqlx::query("ALTER TABLE deposits ADD COLUMN IF NOT EXISTS distribution_id BYTEA")
+        .execute(pool)
+        .await?;
+    // Deposits indexed before distribution ids may have been distributed under the raw source
+    // nonce without being marked processed (e.g. killed before the receipt), and older versions
+    // did not store the tx hash. Flag them all once, when the column is added, so their raw
+    // nonce is checked on the destination before they are sent again.
+    if !column_exists(pool, "deposits", "legacy_nonce").await? {
+        sqlx::query("ALTER TABLE deposits ADD COLUMN legacy_nonce BOOLEAN NOT NULL DEFAULT FALSE")
+            .execute(pool)
+            .await?;
+        let flagged = sqlx::query("UPDATE deposits SET legacy_nonce = TRUE WHERE distribution_id IS NULL")
+            .execute(pool)
+            .await?
+            .rows_affected();
+        if flagged > 0 {
+            println!("Flagged {} deposits indexed before distribution ids as possibly sent with their raw nonce", flagged);
+        }
+    }
+    sqlx::query("CREATE UNIQUE INDEX IF NOT EXISTS idx_deposits_distribution_id ON deposits(distribution_id)")
+        .execute(pool)
+        .await?;
+
+    // Amount paid out in destination token units, and the source-unit remainder lost to fewer decimals
+    sqlx::query("ALTER TABLE deposits ADD COLUMN IF NOT EXISTS distribution_amount NUMERIC(78,0)")
+        .execute(pool)
+        .await?;
+    sqlx::query("ALTER TABLE deposits ADD COLUMN IF NOT EXISTS dust NUMERIC(78,0)")
+        .execute(pool)
+        .await?;
+
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS distribution_transactions (
+            tx_hash BYTEA PRIMARY KEY,
+            deposit_id UUID NOT NULL REFERENCES deposits(deposit_id),
+            chain_id TEXT NOT NULL,
+            account_nonce BIGINT NOT NULL,
+            max_fee_per_gas TEXT NOT NULL,
+            max_priority_fee_per_gas TEXT NOT NULL,
+            replaces_tx_hash BYTEA,
+            created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP
+        )
+        "#,
+    )
+    .execute(pool)
+    .await?;
+
+    // Audit trail of operator approvals; one per operator and deposit
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS deposit_approvals (
+            deposit_id UUID NOT NULL REFERENCES deposits(deposit_id),
+            approver BYTEA NOT NULL,
+            signature TEXT NOT NULL,
+            created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+            PRIMARY KEY (deposit_id, approver)
+        )
+        "#,
+    )
+    .execute(pool)
+    .await?;
+
+    // Fee ledger: one row per deposit, updated if a later attempt charges a different fee
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS fees (
+            deposit_id UUID PRIMARY KEY REFERENCES deposits(deposit_id),
+            chain_id TEXT NOT NULL,
+            token_address BYTEA NOT NULL,
+            fee_model TEXT NOT NULL,
+            amount NUMERIC(78,0) NOT NULL,
+            gross_amount NUMERIC(78,0) NOT NULL,
+            created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP
+        )
+        "#,
+    )
+    .execute(pool)
+    .await?;
+
+    // Deposits in tokens the indexer does not bridge, found by gap detection; their nonces are not gaps
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS ignored_deposits (
+            chain_id TEXT NOT NULL,
+            nonce NUMERIC(78,0) NOT NULL,
+            token_address BYTEA NOT NULL CONSTRAINT ignored_deposits_token_address_length CHECK (octet_length(token_address) = 20),
+            transaction_hash BYTEA NOT NULL CONSTRAINT ignored_deposits_transaction_hash_length CHECK (octet_length(transaction_hash) = 32),
+            block_number BIGINT NOT NULL,
+            created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+            PRIMARY KEY (chain_id, nonce)
         )
         "#,
     )
@@ -67,73 +409,219 @@ pub async fn init_db(pool: &PgPool) -> Result<(), Error> {
     .execute(pool)
     .await?;
 
+    // Amounts and nonces used to be TEXT; convert them in place (only once, as the type
+    // change rewrites the table)
+    for (table, column) in [
+        ("deposits", "amount"),
+        ("deposits", "nonce"),
+        ("deposits", "distribution_amount"),
+        ("deposits", "dust"),
+        ("fees", "amount"),
+        ("fees", "gross_amount"),
+    ] {
+        if is_text_column(pool, table, column).await? {
+            println!("Converting {}.{} to NUMERIC(78,0)", table, column);
+            sqlx::query(&format!(
+                "ALTER TABLE {table} ALTER COLUMN {column} TYPE NUMERIC(78,0) USING {column}::NUMERIC(78,0)"
+            ))
+            .execute(pool)
+            .await?;
+        }
+    }
+
+    // Addresses and hashes used to be 0x-prefixed hex TEXT; convert them to raw bytes,
+    // then pin every column to its byte length
+    for (table, column, length) in [
+        ("deposits", "transaction_hash", 32),
+        ("deposits", "block_hash", 32),
+        ("deposits", "bridge_address", 20),
+        ("deposits", "token_address", 20),
+        ("deposits", "from_address", 20),
+        ("deposits", "to_address", 20),
+        ("deposits", "distribution_tx_hash", 32),
+        ("deposits", "distribution_id", 32),
+        ("distribution_transactions", "tx_hash", 32),
+        ("distribution_transactions", "replaces_tx_hash", 32),
+        ("deposit_approvals", "approver", 20),
+        ("fees", "token_address", 20),
+    ] {
+        if is_text_column(pool, table, column).await? {
+            println!("Converting {}.{} to BYTEA", table, column);
+            sqlx::query(&format!(
+                "ALTER TABLE {table} ALTER COLUMN {column} TYPE BYTEA USING decode(substring({column} FROM 3), 'hex')"
+            ))
+            .execute(pool)
+            .await?;
+        }
+        let constraint = format!("{table}_{column}_length");
+        let exists: bool = sqlx::query_scalar("SELECT EXISTS (SELECT 1 FROM pg_constraint WHERE conname = $1)")
+            .bind(&constraint)
+            .fetch_one(pool)
+            .await?;
+        if !exists {
+            sqlx::query(&format!(
+                "ALTER TABLE {table} ADD CONSTRAINT {constraint} CHECK (octet_length({column}) = {length})"
+            ))
+            .execute(pool)
+            .await?;
+        }
+    }
+
+    // Lookups served by the API: by source tx hash, by (chain, nonce) and by sender or recipient
+    for index in [
+        "CREATE INDEX IF NOT EXISTS idx_deposits_transaction_hash ON deposits(transaction_hash)",
+        "CREATE INDEX IF NOT EXISTS idx_deposits_chain_nonce ON deposits(chain_id, nonce)",
+        "CREATE INDEX IF NOT EXISTS idx_deposits_from_address ON deposits(from_address, created_at)",
+        "CREATE INDEX IF NOT EXISTS idx_deposits_to_address ON deposits(to_address, created_at)",
+    ] {
+        sqlx::query(index).execute(pool).await?;
+    }
+
     Ok(())
 }
 
-pub async fn insert_deposit(pool: &PgPool, deposit: &Deposit) -> Result<(), Error> {
-    if let Err(e) = U256::from_dec_str(&deposit.nonce) {
-        eprintln!("Invalid nonce for deposit: {} (error: {})", deposit.nonce, e);
-        return Ok(());
+async fn column_exists(pool: &PgPool, table: &str, column: &str) -> Result<bool, Error> {
+    sqlx::query_scalar("SELECT EXISTS (SELECT 1 FROM information_schema.columns WHERE table_name = $1 AND column_name = $2)")
+        .bind(table)
+        .bind(column)
+        .fetch_one(pool)
+        .await
+}
+
+// TEXT or VARCHAR (as created by init.sql)
+async fn is_text_column(pool: &PgPool, table: &str, column: &str) -> Result<bool, Error> {
+    let data_type: Option<String> = sqlx::query_scalar(
+        "SELECT data_type::TEXT FROM information_schema.columns WHERE table_name = $1 AND column_name = $2",
+    )
+    .bind(table)
+    .bind(column)
+    .fetch_optional(pool)
+    .await?;
+    Ok(matches!(data_type.as_deref(), Some("text" | "character varying")))
+}
+
+// Returns true if the deposit was inserted, false if it was skipped
+pub async fn insert_deposit(pool: &PgPool, deposit: &Deposit) -> Result<bool, Error> {
+    insert_deposit_with(&mut *pool.acquire().await?, deposit).await
+}
+
+// Insert the deposits of one block (or backfilled block range) and, if `cursor` is set, advance
+// the chain's last processed block to it, all or nothing. Returns the ids of the new deposits.
+pub async fn insert_block_deposits(
+    pool: &PgPool,
+    chain_id: &str,
+    deposits: &[Deposit],
+    cursor: Option<i64>,
+) -> Result<Vec<Uuid>, Error> {
+    let mut tx = pool.begin().await?;
+    let mut inserted = Vec::new();
+    for deposit in deposits {
+        if insert_deposit_with(&mut tx, deposit).await? {
+            inserted.push(deposit.deposit_id);
+        }
+    }
+    if let Some(block_number) = cursor {
+        update_last_processed_block(&mut tx, chain_id, block_number).await?;
+    }
+    tx.commit().await?;
+    Ok(inserted)
+}
+
+async fn insert_deposit_with(conn: &mut PgConnection, deposit: &Deposit) -> Result<bool, Error> {
+    // Rows indexed before log positions were stored are claimed and filled in instead of inserted again
+    let claimed = sqlx::query(
+        r#"
+        UPDATE deposits
+        SET log_index = $5, block_hash = $6, block_timestamp = $7, tx_index = $8, bridge_address = $9
+        WHERE chain_id = $1 AND transaction_hash = $2 AND nonce = $3 AND block_number = $4 AND log_index IS NULL
+        "#,
+    )
+    .bind(&deposit.chain_id)
+    .bind(deposit.transaction_hash)
+    .bind(&deposit.nonce)
+    .bind(deposit.block_number)
+    .bind(deposit.log_index)
+    .bind(deposit.block_hash)
+    .bind(deposit.block_timestamp)
+    .bind(deposit.tx_index)
+    .bind(deposit.bridge_address)
+    .execute(&mut *conn)
+    .await?;
+    if claimed.rows_affected() > 0 {
+        println!("Skipping duplicate deposit: {} (nonce: {}, chain: {})",
+            deposit.transaction_hash, deposit.nonce, deposit.chain_id);
+        return Ok(false);
     }
-    match sqlx::query(
+
+    let result = sqlx::query(
         r#"
         INSERT INTO deposits (
-            deposit_id, chain_id, transaction_hash, block_number, token_address,
+            deposit_id, chain_id, transaction_hash, log_index, block_number, block_hash,
+            block_timestamp, tx_index, bridge_address, token_address,
             from_address, to_address, amount, nonce, processed, finality_confirmed,
-            created_at, updated_at
+            distribution_attempts, status, distribution_id, distribution_amount, dust, created_at, updated_at
+        )
+        VALUES (
+            $1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, $18, $19, $20, $21, $22, $23
         )
-        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13)
+        ON CONFLICT (chain_id, transaction_hash, log_index) DO NOTHING
         "#,
     )
     .bind(deposit.deposit_id)
     .bind(&deposit.chain_id)
-    .bind(&deposit.transaction_hash)
+    .bind(deposit.transaction_hash)
+    .bind(deposit.log_index)
     .bind(deposit.block_number)
-    .bind(&deposit.token_address)
-    .bind(&deposit.from_address)
-    .bind(&deposit.to_address)
+    .bind(deposit.block_hash)
+    .bind(deposit.block_timestamp)
+    .bind(deposit.tx_index)
+    .bind(deposit.bridge_address)
+    .bind(deposit.token_address)
+    .bind(deposit.from_address)
+    .bind(deposit.to_address)
     .bind(&deposit.amount)
     .bind(&deposit.nonce)
     .bind(deposit.processed.unwrap_or(false))
     .bind(deposit.finality_confirmed.unwrap_or(false))
+    .bind(deposit.distribution_attempts)
+    .bind(&deposit.status)
+    .bind(deposit.distribution_id)
+    .bind(&deposit.distribution_amount)
+    .bind(&deposit.dust)
     .bind(deposit.created_at.unwrap_or_else(Utc::now))
     .bind(deposit.updated_at.unwrap_or_else(Utc::now))
-    .execute(pool)
-    .await {
-        Ok(_) => {
-            println!("Deposit inserted: {:?}", deposit.deposit_id);
-            Ok(())
-        }
-        Err(e) if e.to_string().contains("duplicate key value") => {
-            println!("Skipping duplicate deposit: {} (nonce: {}, chain: {})", 
-                deposit.transaction_hash, deposit.nonce, deposit.chain_id);
-            Ok(())
-        }
-        Err(e) => Err(e),
+    .execute(&mut *conn)
+    .await?;
+    if result.rows_affected() == 0 {
+        println!("Skipping duplicate deposit: {} (nonce: {}, chain: {})",
+            deposit.transaction_hash, deposit.nonce, deposit.chain_id);
+        return Ok(false);
     }
+    println!("Deposit inserted: {:?}", deposit.deposit_id);
+    Ok(true)
 }
 
-pub async fn get_last_processed_block(pool: &PgPool, chain_id: &str) -> Result<i64, Error> {
-    let row: (i64,) = sqlx::query_as("SELECT last_block FROM last_processed_blocks WHERE chain_id = $1")
+// None until the chain's first range has been scanned
+pub async fn get_last_processed_block(pool: &PgPool, chain_id: &str) -> Result<Option<i64>, Error> {
+    sqlx::query_scalar("SELECT last_block FROM last_processed_blocks WHERE chain_id = $1")
         .bind(chain_id)
         .fetch_optional(pool)
-        .await?
-        .unwrap_or((0,));
-    Ok(row.0)
+        .await
 }
 
-pub async fn update_last_processed_block(pool: &PgPool, chain_id: &str, block_number: i64) -> Result<(), Error> {
+// The cursor never moves backwards, so a late write for an older block cannot rewind it
+async fn update_last_processed_block(conn: &mut PgConnection, chain_id: &str, block_number: i64) -> Result<(), Error> {
     sqlx::query(
         r#"
         INSERT INTO last_processed_blocks (chain_id, last_block)
         VALUES ($1, $2)
         ON CONFLICT (chain_id)
-        DO UPDATE SET last_block = $2
+        DO UPDATE SET last_block = GREATEST(last_processed_blocks.last_block, $2)
         "#,
     )
     .bind(chain_id)
     .bind(block_number)
-    .execute(pool)
+    .execute(&mut *conn)
     .await?;
     Ok(())
 }
@@ -142,7 +630,8 @@ pub async fn get_unprocessed_deposits(pool: &PgPool) -> Result<Vec<Deposit>, Err
     let deposits = sqlx::query_as::<_, Deposit>(
         r#"
         SELECT * FROM deposits
-        WHERE processed = FALSE AND finality_confirmed = TRUE
+        WHERE processed = FALSE AND finality_confirmed = TRUE AND status = 'pending'
+        ORDER BY COALESCE(block_timestamp, created_at), log_index
         "#,
     )
     .fetch_all(pool)
@@ -170,4 +659,411 @@ pub async fn update_deposit_status(
     .execute(pool)
     .await?;
     Ok(())
-}
\ No newline at end of file
+}
+
+pub async fn set_distribution_tx_hash(pool: &PgPool, deposit_id: Uuid, tx_hash: H256) -> Result<(), Error> {
+    sqlx::query(
+        r#"
+        UPDATE deposits
+        SET distribution_tx_hash = $2, updated_at = CURRENT_TIMESTAMP
+        WHERE deposit_id = $1
+        "#,
+    )
+    .bind(deposit_id)
+    .bind(DbHash(tx_hash))
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+pub async fn set_revert_reason(pool: &PgPool, deposit_id: Uuid, reason: Option<&str>) -> Result<(), Error> {
+    sqlx::query(
+        r#"
+        UPDATE deposits
+        SET revert_reason = $2, updated_at = CURRENT_TIMESTAMP
+        WHERE deposit_id = $1
+        "#,
+    )
+    .bind(deposit_id)
+    .bind(reason)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+// Store why a distribution reverted. `counts_as_attempt` is set for reverts that cost gas
+// (mined txs, not simulations); `permanent` stops further retries. Returns the attempt count.
+pub async fn record_distribution_failure(
+    pool: &PgPool,
+    deposit_id: Uuid,
+    reason: &str,
+    counts_as_attempt: bool,
+    permanent: bool,
+) -> Result<i32, Error> {
+    let row: (i32,) = sqlx::query_as(
+        r#"
+        UPDATE deposits
+        SET revert_reason = $2,
+            distribution_attempts = distribution_attempts + CASE WHEN $3 THEN 1 ELSE 0 END,
+            status = CASE WHEN $4 THEN 'failed' ELSE status END,
+            updated_at = CURRENT_TIMESTAMP
+        WHERE deposit_id = $1
+        RETURNING distribution_attempts
+        "#,
+    )
+    .bind(deposit_id)
+    .bind(reason)
+    .bind(counts_as_attempt)
+    .bind(permanent)
+    .fetch_one(pool)
+    .await?;
+    Ok(row.0)
+}
+
+// What the next distribute tx for a deposit pays out and under which id
+pub async fn set_distribution_details(
+    pool: &PgPool,
+    deposit_id: Uuid,
+    distribution_id: H256,
+    amount: U256,
+    dust: U256,
+) -> Result<(), Error> {
+    sqlx::query(
+        r#"
+        UPDATE deposits
+        SET distribution_id = $2, distribution_amount = $3, dust = $4, updated_at = CURRENT_TIMESTAMP
+        WHERE deposit_id = $1
+        "#,
+    )
+    .bind(deposit_id)
+    .bind(DbHash(distribution_id))
+    .bind(to_numeric(amount))
+    .bind(to_numeric(dust))
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+// Other deposits with the same source chain and nonce, or already assigned `distribution_id`
+pub async fn find_colliding_deposits(pool: &PgPool, deposit: &Deposit, distribution_id: H256) -> Result<Vec<Uuid>, Error> {
+    let rows: Vec<(Uuid,)> = sqlx::query_as(
+        r#"
+        SELECT deposit_id FROM deposits
+        WHERE deposit_id <> $1 AND ((chain_id = $2 AND nonce = $3) OR distribution_id = $4)
+        "#,
+    )
+    .bind(deposit.deposit_id)
+    .bind(&deposit.chain_id)
+    .bind(&deposit.nonce)
+    .bind(DbHash(distribution_id))
+    .fetch_all(pool)
+    .await?;
+    Ok(rows.into_iter().map(|row| row.0).collect())
+}
+
+// Move a deposit out of the processor's queue (e.g. 'held_for_review') with the reason
+pub async fn set_deposit_status(pool: &PgPool, deposit_id: Uuid, status: &str, reason: Option<&str>) -> Result<(), Error> {
+    sqlx::query(
+        r#"
+        UPDATE deposits
+        SET status = $2, status_reason = $3, updated_at = CURRENT_TIMESTAMP
+        WHERE deposit_id = $1
+        "#,
+    )
+    .bind(deposit_id)
+    .bind(status)
+    .bind(reason)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+pub async fn get_deposit(pool: &PgPool, deposit_id: Uuid) -> Result<Option<Deposit>, Error> {
+    sqlx::query_as::<_, Deposit>("SELECT * FROM deposits WHERE deposit_id = $1")
+        .bind(deposit_id)
+        .fetch_optional(pool)
+        .await
+}
+
+// Mark a chain's deposits at or below `block_number` final. Returns the ids of the deposits marked.
+pub async fn confirm_finality(pool: &PgPool, chain_id: &str, block_number: i64) -> Result<Vec<Uuid>, Error> {
+    sqlx::query_scalar(
+        r#"
+        UPDATE deposits
+        SET finality_confirmed = TRUE, updated_at = CURRENT_TIMESTAMP
+        WHERE chain_id = $1 AND block_number <= $2 AND finality_confirmed = FALSE
+        RETURNING deposit_id
+        "#,
+    )
+    .bind(chain_id)
+    .bind(block_number)
+    .fetch_all(pool)
+    .await
+}
+
+// All deposits emitted by a source transaction, in log order
+pub async fn get_deposits_by_tx_hash(pool: &PgPool, tx_hash: H256) -> Result<Vec<Deposit>, Error> {
+    sqlx::query_as::<_, Deposit>("SELECT * FROM deposits WHERE transaction_hash = $1 ORDER BY chain_id, log_index")
+        .bind(DbHash(tx_hash))
+        .fetch_all(pool)
+        .await
+}
+
+// Normally one deposit; several only if the nonce collided (see find_colliding_deposits)
+pub async fn get_deposits_by_nonce(pool: &PgPool, chain_id: &str, nonce: U256) -> Result<Vec<Deposit>, Error> {
+    sqlx::query_as::<_, Deposit>("SELECT * FROM deposits WHERE chain_id = $1 AND nonce = $2 ORDER BY created_at")
+        .bind(chain_id)
+        .bind(to_numeric(nonce))
+        .fetch_all(pool)
+        .await
+}
+
+// A page of deposits from `sender` and/or to `recipient`, newest first
+pub async fn get_deposits_by_party(
+    pool: &PgPool,
+    sender: Option<H160>,
+    recipient: Option<H160>,
+    limit: i64,
+    offset: i64,
+) -> Result<Vec<Deposit>, Error> {
+    sqlx::query_as::<_, Deposit>(
+        r#"
+        SELECT * FROM deposits
+        WHERE ($1::BYTEA IS NULL OR from_address = $1) AND ($2::BYTEA IS NULL OR to_address = $2)
+        ORDER BY created_at DESC, deposit_id
+        LIMIT $3 OFFSET $4
+        "#,
+    )
+    .bind(sender.map(DbAddress))
+    .bind(recipient.map(DbAddress))
+    .bind(limit)
+    .bind(offset)
+    .fetch_all(pool)
+    .await
+}
+
+pub async fn get_deposits_by_chain(pool: &PgPool, chain_id: &str) -> Result<Vec<Deposit>, Error> {
+    sqlx::query_as::<_, Deposit>("SELECT * FROM deposits WHERE chain_id = $1")
+        .bind(chain_id)
+        .fetch_all(pool)
+        .await
+}
+
+// (nonce, block_number) of every deposit indexed for a chain
+pub async fn get_indexed_nonces(pool: &PgPool, chain_id: &str) -> Result<Vec<(BigDecimal, i64)>, Error> {
+    sqlx::query_as("SELECT nonce, block_number FROM deposits WHERE chain_id = $1")
+        .bind(chain_id)
+        .fetch_all(pool)
+        .await
+}
+
+// Record a deposit in another token so its nonce is not reported as missing again
+pub async fn insert_ignored_deposit(pool: &PgPool, chain_id: &str, event: &DepositFilter, meta: &LogMeta) -> Result<(), Error> {
+    sqlx::query(
+        r#"
+        INSERT INTO ignored_deposits (chain_id, nonce, token_address, transaction_hash, block_number)
+        VALUES ($1, $2, $3, $4, $5)
+        ON CONFLICT (chain_id, nonce) DO NOTHING
+        "#,
+    )
+    .bind(chain_id)
+    .bind(to_numeric(event.nonce))
+    .bind(DbAddress(event.token))
+    .bind(DbHash(meta.transaction_hash))
+    .bind(meta.block_number.as_u64() as i64)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+pub async fn get_ignored_nonces(pool: &PgPool, chain_id: &str) -> Result<Vec<BigDecimal>, Error> {
+    sqlx::query_scalar("SELECT nonce FROM ignored_deposits WHERE chain_id = $1")
+        .bind(chain_id)
+        .fetch_all(pool)
+        .await
+}
+
+pub async fn get_deposits_by_status(pool: &PgPool, status: &str) -> Result<Vec<Deposit>, Error> {
+    sqlx::query_as::<_, Deposit>("SELECT * FROM deposits WHERE status = $1 ORDER BY COALESCE(block_timestamp, created_at), log_index")
+        .bind(status)
+        .fetch_all(pool)
+        .await
+}
+
+// Put a held deposit back in the queue with its rate limits waived.
+// Returns false if the deposit does not exist or is not held for review.
+pub async fn release_held_deposit(pool: &PgPool, deposit_id: Uuid) -> Result<bool, Error> {
+    let result = sqlx::query(
+        r#"
+        UPDATE deposits
+        SET status = 'pending', status_reason = NULL, limits_waived = TRUE, updated_at = CURRENT_TIMESTAMP
+        WHERE deposit_id = $1 AND status = 'held_for_review'
+        "#,
+    )
+    .bind(deposit_id)
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+// Put an approved deposit back in the queue, dropping the "awaiting approval" reason.
+// Returns false if the deposit is no longer awaiting approval.
+pub async fn release_approved_deposit(pool: &PgPool, deposit_id: Uuid) -> Result<bool, Error> {
+    let result = sqlx::query(
+        r#"
+        UPDATE deposits
+        SET status = 'pending', status_reason = NULL, updated_at = CURRENT_TIMESTAMP
+        WHERE deposit_id = $1 AND status = 'awaiting_approval'
+        "#,
+    )
+    .bind(deposit_id)
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+// Total amount distributed (in destination units) for `token_address` deposits from `chain_id`,
+// optionally to one recipient, whose first distribute tx was sent since `since`, excluding
+// `exclude` and deposits that failed
+pub async fn distributed_amount_since(
+    pool: &PgPool,
+    chain_id: &str,
+    token_address: H160,
+    recipient: Option<H160>,
+    since: DateTime<Utc>,
+    exclude: Uuid,
+) -> Result<U256, Error> {
+    let row: (BigDecimal,) = sqlx::query_as(
+        r#"
+        SELECT COALESCE(SUM(COALESCE(d.distribution_amount, d.amount)), 0) FROM deposits d
+        WHERE d.chain_id = $1 AND d.token_address = $2
+          AND ($3::BYTEA IS NULL OR d.to_address = $3)
+          AND d.deposit_id <> $5 AND d.status <> 'failed'
+          AND EXISTS (
+              SELECT 1 FROM distribution_transactions t
+              WHERE t.deposit_id = d.deposit_id AND t.replaces_tx_hash IS NULL AND t.created_at >= $4
+          )
+        "#,
+    )
+    .bind(chain_id)
+    .bind(DbAddress(token_address))
+    .bind(recipient.map(DbAddress))
+    .bind(since)
+    .bind(exclude)
+    .fetch_one(pool)
+    .await?;
+    from_numeric(&row.0).ok_or_else(|| Error::Decode(format!("distributed amount {} out of range", row.0).into()))
+}
+
+// Returns false if this approver already approved the deposit
+pub async fn insert_deposit_approval(pool: &PgPool, approval: &DepositApproval) -> Result<bool, Error> {
+    let result = sqlx::query(
+        r#"
+        INSERT INTO deposit_approvals (deposit_id, approver, signature)
+        VALUES ($1, $2, $3)
+        ON CONFLICT (deposit_id, approver) DO NOTHING
+        "#,
+    )
+    .bind(approval.deposit_id)
+    .bind(approval.approver)
+    .bind(&approval.signature)
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+pub async fn get_deposit_approvals(pool: &PgPool, deposit_id: Uuid) -> Result<Vec<DepositApproval>, Error> {
+    sqlx::query_as::<_, DepositApproval>("SELECT * FROM deposit_approvals WHERE deposit_id = $1 ORDER BY created_at")
+        .bind(deposit_id)
+        .fetch_all(pool)
+        .await
+}
+
+pub async fn record_fee(pool: &PgPool, fee: &Fee) -> Result<(), Error> {
+    sqlx::query(
+        r#"
+        INSERT INTO fees (deposit_id, chain_id, token_address, fee_model, amount, gross_amount)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        ON CONFLICT (deposit_id) DO UPDATE
+        SET fee_model = $4, amount = $5, gross_amount = $6, created_at = CURRENT_TIMESTAMP
+        "#,
+    )
+    .bind(fee.deposit_id)
+    .bind(&fee.chain_id)
+    .bind(fee.token_address)
+    .bind(&fee.fee_model)
+    .bind(to_numeric(fee.amount))
+    .bind(to_numeric(fee.gross_amount))
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+// Fees per destination token, counting only deposits that were distributed
+pub async fn get_fee_totals(pool: &PgPool) -> Result<Vec<FeeTotal>, Error> {
+    sqlx::query_as::<_, FeeTotal>(
+        r#"
+        SELECT f.chain_id, f.token_address, COUNT(*) AS deposits, SUM(f.amount) AS total
+        FROM fees f JOIN deposits d ON d.deposit_id = f.deposit_id
+        WHERE d.processed = TRUE
+        GROUP BY f.chain_id, f.token_address
+        ORDER BY f.chain_id, f.token_address
+        "#,
+    )
+    .fetch_all(pool)
+    .await
+}
+
+pub async fn mark_deposit_failed(pool: &PgPool, deposit_id: Uuid) -> Result<(), Error> {
+    sqlx::query("UPDATE deposits SET status = 'failed', updated_at = CURRENT_TIMESTAMP WHERE deposit_id = $1")
+        .bind(deposit_id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+// Record a broadcast distribute tx and make it the deposit's current one
+pub async fn insert_distribution_transaction(pool: &PgPool, tx: &DistributionTransaction) -> Result<(), Error> {
+    sqlx::query(
+        r#"
+        INSERT INTO distribution_transactions (
+            tx_hash, deposit_id, chain_id, account_nonce, max_fee_per_gas,
+            max_priority_fee_per_gas, replaces_tx_hash
+        )
+        VALUES ($1, $2, $3, $4, $5, $6, $7)
+        ON CONFLICT (tx_hash) DO NOTHING
+        "#,
+    )
+    .bind(tx.tx_hash)
+    .bind(tx.deposit_id)
+    .bind(&tx.chain_id)
+    .bind(tx.account_nonce)
+    .bind(&tx.max_fee_per_gas)
+    .bind(&tx.max_priority_fee_per_gas)
+    .bind(tx.replaces_tx_hash)
+    .execute(pool)
+    .await?;
+    set_distribution_tx_hash(pool, tx.deposit_id, tx.tx_hash.0).await
+}
+
+// Every distribute tx sent for a deposit, replacements included, oldest first
+pub async fn get_distribution_transactions(pool: &PgPool, deposit_id: Uuid) -> Result<Vec<DistributionTransaction>, Error> {
+    sqlx::query_as::<_, DistributionTransaction>(
+        r#"
+        SELECT tx_hash, deposit_id, chain_id, account_nonce, max_fee_per_gas, max_priority_fee_per_gas, replaces_tx_hash
+        FROM distribution_transactions
+        WHERE deposit_id = $1
+        ORDER BY created_at, account_nonce
+        "#,
+    )
+    .bind(deposit_id)
+    .fetch_all(pool)
+    .await
+}
+
+pub async fn count_unprocessed_deposits(pool: &PgPool) -> Result<i64, Error> {
+    let row: (i64,) = sqlx::query_as("SELECT COUNT(*) FROM deposits WHERE processed = FALSE")
+        .fetch_one(pool)
+        .await?;
+    Ok(row.0)
+}
@@ -0,0 +1,72 @@
+use ethers::prelude::*;
+use crate::config::chain_var;
+
+// What to do when a deposit carries more precision than the destination token can represent
+// ({PREFIX}_DUST_POLICY, default record):
+// - record: distribute the rounded-down amount and store the remainder as the deposit's dust
+// - reject: do not distribute; the deposit is marked rejected
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DustPolicy {
+    Record,
+    Reject,
+}
+
+impl DustPolicy {
+    pub fn from_env(prefix: &str) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
+        match chain_var(prefix, "DUST_POLICY").as_deref() {
+            None | Some("record") => Ok(DustPolicy::Record),
+            Some("reject") => Ok(DustPolicy::Reject),
+            Some(other) => Err(format!("Unknown {}_DUST_POLICY: {} (expected record or reject)", prefix, other).into()),
+        }
+    }
+}
+
+// Convert `amount` from a token with `from` decimals to one with `to` decimals.
+// Returns the converted amount and the dust lost to rounding down, in source units,
+// or None if the result does not fit in a uint256.
+pub fn convert(amount: U256, from: u8, to: u8) -> Option<(U256, U256)> {
+    let scale = U256::from(10).checked_pow(U256::from(from.abs_diff(to)));
+    if to >= from {
+        return amount.checked_mul(scale?).map(|converted| (converted, U256::zero()));
+    }
+    match scale {
+        Some(scale) => Some((amount / scale, amount % scale)),
+        // More than 77 decimals apart: nothing survives the conversion
+        None => Some((U256::zero(), amount)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn same_decimals_is_unchanged() {
+        assert_eq!(convert(U256::from(123_456), 18, 18), Some((U256::from(123_456), U256::zero())));
+    }
+
+    #[test]
+    fn scales_up_to_more_decimals() {
+        assert_eq!(convert(U256::from(1_500_000), 6, 18), Some((U256::exp10(12) * 1_500_000, U256::zero())));
+    }
+
+    #[test]
+    fn scales_down_and_records_dust() {
+        // 1.234567891234567891 tokens with 18 decimals is 1.234567 with 6, losing 891234567891
+        let amount = U256::from_dec_str("1234567891234567891").unwrap();
+        assert_eq!(convert(amount, 18, 6), Some((U256::from(1_234_567), U256::from(891_234_567_891u64))));
+        assert_eq!(convert(U256::from(999_999_999_999u64), 18, 6), Some((U256::zero(), U256::from(999_999_999_999u64))));
+    }
+
+    #[test]
+    fn overflow_scaling_up_is_none() {
+        assert_eq!(convert(U256::MAX, 6, 18), None);
+        assert_eq!(convert(U256::one(), 0, 78), None);
+        assert_eq!(convert(U256::one(), 0, 77), Some((U256::exp10(77), U256::zero())));
+    }
+
+    #[test]
+    fn more_than_77_decimals_down_is_all_dust() {
+        assert_eq!(convert(U256::MAX, 255, 0), Some((U256::zero(), U256::MAX)));
+    }
+}
@@ -0,0 +1,110 @@
+use ethers::prelude::*;
+use crate::config::{amount_var, chain_var};
+
+// Bridge fee deducted from each distribution on a route ({PREFIX}_FEE_MODEL, default none):
+// - flat: FEE_FLAT_AMOUNT destination token units per deposit
+// - bps: FEE_BPS basis points of the amount
+// - gas: the estimated gas cost of the distribute tx, priced with FEE_TOKEN_PER_NATIVE
+//   (destination token units per whole native coin, e.g. per 1 ETH)
+#[derive(Debug, Clone, Copy)]
+pub enum FeeModel {
+    None,
+    Flat(U256),
+    Bps(u32),
+    Gas { token_per_native: U256 },
+}
+
+impl FeeModel {
+    pub fn from_env(prefix: &str) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
+        let required = |name: &str| -> Result<U256, Box<dyn std::error::Error + Send + Sync>> {
+            amount_var(prefix, name)?.ok_or_else(|| format!("{}_{} or {} must be set", prefix, name, name).into())
+        };
+        match chain_var(prefix, "FEE_MODEL").as_deref() {
+            None | Some("none") => Ok(FeeModel::None),
+            Some("flat") => Ok(FeeModel::Flat(required("FEE_FLAT_AMOUNT")?)),
+            Some("bps") => {
+                let bps = required("FEE_BPS")?;
+                if bps > U256::from(10_000) {
+                    return Err(format!("{}_FEE_BPS must be at most 10000", prefix).into());
+                }
+                Ok(FeeModel::Bps(bps.as_u32()))
+            }
+            Some("gas") => Ok(FeeModel::Gas { token_per_native: required("FEE_TOKEN_PER_NATIVE")? }),
+            Some(other) => Err(format!("Unknown {}_FEE_MODEL: {} (expected none, flat, bps or gas)", prefix, other).into()),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            FeeModel::None => "none",
+            FeeModel::Flat(_) => "flat",
+            FeeModel::Bps(_) => "bps",
+            FeeModel::Gas { .. } => "gas",
+        }
+    }
+
+    // Gas-based fees move with the market, so a deposit they swallow can be retried later
+    pub fn is_fixed(&self) -> bool {
+        !matches!(self, FeeModel::Gas { .. })
+    }
+
+    // Fee for distributing `amount` with a tx using `gas` at up to `max_fee_per_gas` wei per gas
+    pub fn fee(&self, amount: U256, gas: U256, max_fee_per_gas: U256) -> U256 {
+        match *self {
+            FeeModel::None => U256::zero(),
+            FeeModel::Flat(fee) => fee,
+            FeeModel::Bps(bps) => amount.full_mul(U256::from(bps)).checked_div(10_000.into())
+                .and_then(|fee| U256::try_from(fee).ok())
+                .unwrap_or(U256::MAX),
+            FeeModel::Gas { token_per_native } => {
+                let cost = gas.saturating_mul(max_fee_per_gas);
+                U256::try_from(cost.full_mul(token_per_native) / U256::exp10(18)).unwrap_or(U256::MAX)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gwei(value: u64) -> U256 {
+        U256::from(value) * U256::exp10(9)
+    }
+
+    #[test]
+    fn none_and_flat_ignore_amount_and_gas() {
+        assert_eq!(FeeModel::None.fee(U256::from(1_000), U256::from(21_000), gwei(1)), U256::zero());
+        assert_eq!(FeeModel::Flat(U256::from(42)).fee(U256::from(1_000), U256::from(21_000), gwei(1)), U256::from(42));
+    }
+
+    #[test]
+    fn bps_rounds_down() {
+        // 0.3% of 1000 is 3; of 999 it is 2.997
+        assert_eq!(FeeModel::Bps(30).fee(U256::from(1_000), U256::zero(), U256::zero()), U256::from(3));
+        assert_eq!(FeeModel::Bps(30).fee(U256::from(999), U256::zero(), U256::zero()), U256::from(2));
+        assert_eq!(FeeModel::Bps(1).fee(U256::from(9_999), U256::zero(), U256::zero()), U256::zero());
+        assert_eq!(FeeModel::Bps(10_000).fee(U256::from(1_000), U256::zero(), U256::zero()), U256::from(1_000));
+    }
+
+    #[test]
+    fn bps_of_huge_amounts_does_not_overflow() {
+        // amount * bps exceeds a U256; the U512 product keeps it exact
+        let expected = U256::MAX / 10_000 * 5_000 + U256::MAX % 10_000 * 5_000 / 10_000;
+        assert_eq!(FeeModel::Bps(5_000).fee(U256::MAX, U256::zero(), U256::zero()), expected);
+        assert_eq!(FeeModel::Bps(10_000).fee(U256::MAX, U256::zero(), U256::zero()), U256::MAX);
+    }
+
+    #[test]
+    fn gas_fee_prices_the_tx_in_tokens() {
+        // 100,000 gas at 20 gwei is 0.002 ETH; at 2,000 tokens (6 decimals) per ETH that is 4 tokens
+        let model = FeeModel::Gas { token_per_native: U256::from(2_000_000_000u64) };
+        assert_eq!(model.fee(U256::zero(), U256::from(100_000), gwei(20)), U256::from(4_000_000));
+    }
+
+    #[test]
+    fn gas_fee_saturates_instead_of_overflowing() {
+        let model = FeeModel::Gas { token_per_native: U256::MAX };
+        assert_eq!(model.fee(U256::zero(), U256::MAX, U256::MAX), U256::MAX);
+    }
+}
@@ -0,0 +1,204 @@
+use ethers::prelude::*;
+use sqlx::PgPool;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::sync::Arc;
+use tokio::time::Duration;
+use crate::abi::TokenBridge;
+use crate::alert;
+use crate::bridge::{self, LOG_CHUNK_BLOCKS};
+use crate::db::{self, Deposit};
+use crate::shutdown::{Shutdown, Stats};
+use crate::updates::Updates;
+
+// A source bridge whose Deposit nonces are checked for gaps
+pub struct SourceBridge {
+    pub name: &'static str,
+    pub chain_id: &'static str,
+    pub contract: TokenBridge<Provider<Ws>>,
+    // Only deposits of this token are indexed
+    pub token: H160,
+    // Only needed to backfill below the first indexed deposit
+    pub deploy_block: Option<u64>,
+    pub updates: Updates,
+}
+
+// Nonces below `on_chain` (the bridge's getNonce()) that are not in `indexed`
+pub fn missing_nonces(indexed: &BTreeSet<U256>, on_chain: U256) -> Vec<U256> {
+    let mut missing = Vec::new();
+    let mut nonce = U256::zero();
+    while nonce < on_chain {
+        if !indexed.contains(&nonce) {
+            missing.push(nonce);
+        }
+        nonce += U256::one();
+    }
+    missing
+}
+
+// Group sorted nonces into inclusive runs of consecutive values
+fn ranges(nonces: &[U256]) -> Vec<(U256, U256)> {
+    let mut ranges: Vec<(U256, U256)> = Vec::new();
+    for &nonce in nonces {
+        match ranges.last_mut() {
+            Some((_, end)) if *end + 1 == nonce => *end = nonce,
+            _ => ranges.push((nonce, nonce)),
+        }
+    }
+    ranges
+}
+
+// Gap Detection Loop: deposit nonces come from a strictly incrementing counter, so a nonce
+// below getNonce() that is not indexed is a missed event. Each run of missing nonces is
+// backfilled with eth_getLogs over the blocks between its indexed neighbours.
+pub async fn run(pool: PgPool, sources: Vec<SourceBridge>, stats: Arc<Stats>, interval: Duration, mut shutdown: Shutdown) {
+    loop {
+        for source in &sources {
+            if shutdown.is_requested() {
+                break;
+            }
+            if let Err(e) = check(&pool, source, &stats).await {
+                eprintln!("Failed to check {} deposits for nonce gaps: {}", source.name, e);
+            }
+        }
+        if shutdown.sleep(interval).await {
+            break;
+        }
+    }
+    println!("Gap detection loop stopped");
+}
+
+async fn check(pool: &PgPool, source: &SourceBridge, stats: &Stats) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    // Only deposits up to the listener's cursor are guaranteed to be indexed. Reading getNonce()
+    // at that block keeps deposits the listener has not reached yet (e.g. while it catches up
+    // after downtime) from looking missing.
+    let Some(scanned) = db::get_last_processed_block(pool, source.chain_id).await? else {
+        return Ok(());
+    };
+    let scanned = scanned as u64;
+    let on_chain = source.contract.get_nonce().block(scanned).call().await?;
+    let indexed: BTreeMap<U256, u64> = db::get_indexed_nonces(pool, source.chain_id).await?
+        .into_iter()
+        .filter_map(|(nonce, block)| Some((db::from_numeric(&nonce)?, block as u64)))
+        .collect();
+    // Nonces of deposits in other tokens, which the indexer deliberately skips
+    let ignored = db::get_ignored_nonces(pool, source.chain_id).await?;
+    let known: BTreeSet<U256> = indexed.keys().copied()
+        .chain(ignored.iter().filter_map(db::from_numeric))
+        .collect();
+    let missing = missing_nonces(&known, on_chain);
+    if missing.is_empty() {
+        return Ok(());
+    }
+
+    let (mut recovered, mut skipped) = (0, 0);
+    for (first, last) in ranges(&missing) {
+        // The missing events lie between the blocks of the indexed deposits around them
+        let from = match indexed.range(..first).next_back() {
+            Some((_, block)) => *block,
+            None => source.deploy_block.ok_or_else(|| format!(
+                "{} nonces {}..={} are missing below the first indexed deposit; set the bridge deploy block to backfill them",
+                source.name, first, last
+            ))?,
+        };
+        let to = indexed.range(last + 1..).next().map(|(_, block)| *block).unwrap_or(scanned);
+        let (range_recovered, range_skipped) = backfill(pool, source, from, to, (first, last), stats).await?;
+        println!(
+            "Backfilled {} of {} missing {} deposits (nonces {}..={}) from blocks {}..={}; {} were in other tokens",
+            range_recovered, last - first + 1, source.name, first, last, from, to, range_skipped
+        );
+        recovered += range_recovered;
+        skipped += range_skipped;
+    }
+
+    // Other-token deposits are recorded and skipped from now on; only real gaps are alerted
+    let gaps = missing.len() as u64 - skipped;
+    if gaps > 0 {
+        alert::raise(&format!(
+            "{} deposits missing on {} (getNonce() = {} at block {}, first missing nonce {}); backfilled {}",
+            gaps, source.name, on_chain, scanned, missing[0], recovered
+        )).await;
+    }
+    Ok(())
+}
+
+// Index the deposits with nonces `first..=last` in blocks `from..=to`. Returns how many were
+// recovered and how many were in other tokens.
+async fn backfill(
+    pool: &PgPool,
+    source: &SourceBridge,
+    from: u64,
+    to: u64,
+    (first, last): (U256, U256),
+    stats: &Stats,
+) -> Result<(u64, u64), Box<dyn std::error::Error + Send + Sync>> {
+    let (mut recovered, mut skipped) = (0, 0);
+    let mut timestamps = HashMap::new();
+    let mut start = from;
+    while start <= to {
+        let end = (start + LOG_CHUNK_BLOCKS - 1).min(to);
+        let events = source.contract.deposit_filter().from_block(start).to_block(end).query_with_meta().await?;
+        for (event, meta) in events {
+            if event.nonce < first || event.nonce > last {
+                continue;
+            }
+            if event.token != source.token {
+                db::insert_ignored_deposit(pool, source.chain_id, &event, &meta).await?;
+                skipped += 1;
+                continue;
+            }
+            let block_timestamp = match timestamps.get(&meta.block_hash) {
+                Some(timestamp) => *timestamp,
+                None => {
+                    let timestamp = bridge::block_timestamp(source.contract.client().as_ref(), meta.block_hash).await;
+                    timestamps.insert(meta.block_hash, timestamp);
+                    timestamp
+                }
+            };
+            let deposit = Deposit::from_event(source.chain_id, &event, &meta, block_timestamp);
+            if db::insert_deposit(pool, &deposit).await? {
+                Stats::incr(&stats.deposits_indexed);
+                source.updates.notify(deposit.deposit_id);
+                recovered += 1;
+            }
+        }
+        start = end + 1;
+    }
+    Ok((recovered, skipped))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn nonces(values: &[u64]) -> Vec<U256> {
+        values.iter().map(|&value| U256::from(value)).collect()
+    }
+
+    #[test]
+    fn no_deposits_means_no_gaps() {
+        assert!(missing_nonces(&BTreeSet::new(), U256::zero()).is_empty());
+        assert!(missing_nonces(&nonces(&[0, 1, 2]).into_iter().collect(), U256::from(3)).is_empty());
+    }
+
+    #[test]
+    fn finds_nonces_below_on_chain_counter() {
+        let indexed: BTreeSet<U256> = nonces(&[0, 2, 3, 6]).into_iter().collect();
+        assert_eq!(missing_nonces(&indexed, U256::from(8)), nonces(&[1, 4, 5, 7]));
+        // Indexed nonces at or above getNonce() are not gaps
+        assert_eq!(missing_nonces(&indexed, U256::from(2)), nonces(&[1]));
+    }
+
+    #[test]
+    fn groups_consecutive_nonces_into_ranges() {
+        assert!(ranges(&[]).is_empty());
+        assert_eq!(ranges(&nonces(&[4])), vec![(U256::from(4), U256::from(4))]);
+        assert_eq!(
+            ranges(&nonces(&[1, 4, 5, 6, 9, 10])),
+            vec![
+                (U256::from(1), U256::from(1)),
+                (U256::from(4), U256::from(6)),
+                (U256::from(9), U256::from(10)),
+            ]
+        );
+    }
+}
@@ -0,0 +1,95 @@
+use ethers::prelude::*;
+use ethers::types::transaction::eip2718::TypedTransaction;
+use ethers::utils::parse_units;
+use crate::config::chain_var;
+
+// Per-chain EIP-1559 fee policy for distribute transactions, from {PREFIX}_* or shared env vars:
+// - MAX_FEE_PER_GAS_GWEI / MAX_PRIORITY_FEE_PER_GAS_GWEI: fixed fees (default: node estimate)
+// - GAS_PRICE_CAP_GWEI: distributions are deferred while the max fee would exceed this
+// - REPLACE_AFTER_BLOCKS: resend with bumped fees if a tx is not mined within N blocks (0 disables)
+// - FEE_BUMP_PERCENT: fee increase per replacement; nodes require at least 10
+#[derive(Debug, Clone)]
+pub struct GasPolicy {
+    max_fee_per_gas: Option<U256>,
+    max_priority_fee_per_gas: Option<U256>,
+    cap: Option<U256>,
+    pub replace_after_blocks: u64,
+    bump_percent: u64,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Fees {
+    pub max_fee_per_gas: U256,
+    pub max_priority_fee_per_gas: U256,
+}
+
+fn gwei_var(prefix: &str, name: &str) -> Result<Option<U256>, Box<dyn std::error::Error + Send + Sync>> {
+    match chain_var(prefix, name) {
+        Some(value) => Ok(Some(parse_units(value.trim(), "gwei")?.into())),
+        None => Ok(None),
+    }
+}
+
+impl GasPolicy {
+    pub fn from_env(prefix: &str) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
+        let bump_percent: u64 = chain_var(prefix, "FEE_BUMP_PERCENT").unwrap_or_else(|| "15".to_string()).parse()?;
+        if bump_percent < 10 {
+            return Err(format!("{}_FEE_BUMP_PERCENT must be at least 10", prefix).into());
+        }
+        Ok(Self {
+            max_fee_per_gas: gwei_var(prefix, "MAX_FEE_PER_GAS_GWEI")?,
+            max_priority_fee_per_gas: gwei_var(prefix, "MAX_PRIORITY_FEE_PER_GAS_GWEI")?,
+            cap: gwei_var(prefix, "GAS_PRICE_CAP_GWEI")?,
+            replace_after_blocks: chain_var(prefix, "REPLACE_AFTER_BLOCKS").unwrap_or_else(|| "10".to_string()).parse()?,
+            bump_percent,
+        })
+    }
+
+    // Fees for a new transaction: configured values, falling back to the node's estimate
+    pub async fn fees<M: Middleware>(&self, client: &M) -> Result<Fees, M::Error> {
+        let (estimated_max_fee, estimated_priority_fee) = match (self.max_fee_per_gas, self.max_priority_fee_per_gas) {
+            (Some(max_fee), Some(priority_fee)) => (max_fee, priority_fee),
+            _ => client.estimate_eip1559_fees(None).await?,
+        };
+        let max_priority_fee_per_gas = self.max_priority_fee_per_gas.unwrap_or(estimated_priority_fee);
+        let max_fee_per_gas = self.max_fee_per_gas.unwrap_or(estimated_max_fee).max(max_priority_fee_per_gas);
+        Ok(Fees { max_fee_per_gas, max_priority_fee_per_gas })
+    }
+
+    pub fn exceeds_cap(&self, fees: &Fees) -> bool {
+        self.cap.is_some_and(|cap| fees.max_fee_per_gas > cap)
+    }
+
+    // Fees for a replacement: the previous fees bumped by FEE_BUMP_PERCENT, or the current market if higher
+    pub fn bump(&self, previous: &Fees, current: &Fees) -> Fees {
+        let bump = |fee: U256| fee * (100 + self.bump_percent) / 100 + 1;
+        let max_priority_fee_per_gas = bump(previous.max_priority_fee_per_gas).max(current.max_priority_fee_per_gas);
+        let max_fee_per_gas = bump(previous.max_fee_per_gas)
+            .max(current.max_fee_per_gas)
+            .max(max_priority_fee_per_gas);
+        Fees { max_fee_per_gas, max_priority_fee_per_gas }
+    }
+}
+
+impl Fees {
+    pub fn apply(&self, tx: &mut TypedTransaction) {
+        match tx {
+            TypedTransaction::Eip1559(inner) => {
+                inner.max_fee_per_gas = Some(self.max_fee_per_gas);
+                inner.max_priority_fee_per_gas = Some(self.max_priority_fee_per_gas);
+            }
+            _ => {
+                tx.set_gas_price(self.max_fee_per_gas);
+            }
+        }
+    }
+
+    // Fees of an already broadcast transaction
+    pub fn of(tx: &Transaction) -> Self {
+        let gas_price = tx.gas_price.unwrap_or_default();
+        Fees {
+            max_fee_per_gas: tx.max_fee_per_gas.unwrap_or(gas_price),
+            max_priority_fee_per_gas: tx.max_priority_fee_per_gas.unwrap_or(gas_price),
+        }
+    }
+}
@@ -0,0 +1,57 @@
+use chrono::{Duration, Utc};
+use ethers::prelude::*;
+use sqlx::PgPool;
+use crate::config::amount_var;
+use crate::db::{self, Deposit};
+
+// Payout limits for the deposits of one route, from {PREFIX}_* or shared env vars, in base
+// units of the distributed token (unset means no limit):
+// - MAX_DEPOSIT_AMOUNT: largest single deposit distributed automatically
+// - MAX_AMOUNT_PER_HOUR / MAX_AMOUNT_PER_DAY: total distributed over a rolling hour / day
+// - MAX_RECIPIENT_AMOUNT_PER_DAY: total distributed to one recipient over a rolling day
+#[derive(Debug, Clone, Default)]
+pub struct RateLimits {
+    max_per_deposit: Option<U256>,
+    max_per_hour: Option<U256>,
+    max_per_day: Option<U256>,
+    max_per_recipient_per_day: Option<U256>,
+}
+
+impl RateLimits {
+    pub fn from_env(prefix: &str) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
+        Ok(Self {
+            max_per_deposit: amount_var(prefix, "MAX_DEPOSIT_AMOUNT")?,
+            max_per_hour: amount_var(prefix, "MAX_AMOUNT_PER_HOUR")?,
+            max_per_day: amount_var(prefix, "MAX_AMOUNT_PER_DAY")?,
+            max_per_recipient_per_day: amount_var(prefix, "MAX_RECIPIENT_AMOUNT_PER_DAY")?,
+        })
+    }
+
+    // The limit distributing `amount` for this deposit would exceed, or None if it may go out
+    pub async fn exceeded(&self, pool: &PgPool, deposit: &Deposit, amount: U256) -> Result<Option<String>, sqlx::Error> {
+        if let Some(max) = self.max_per_deposit {
+            if amount > max {
+                return Ok(Some(format!("amount {} exceeds the per-deposit limit of {}", amount, max)));
+            }
+        }
+
+        let windows = [
+            (self.max_per_hour, Duration::hours(1), None, "hourly"),
+            (self.max_per_day, Duration::days(1), None, "daily"),
+            (self.max_per_recipient_per_day, Duration::days(1), Some(deposit.to_address.0), "daily per-recipient"),
+        ];
+        for (max, window, recipient, name) in windows {
+            let Some(max) = max else { continue };
+            let distributed = db::distributed_amount_since(
+                pool, &deposit.chain_id, deposit.token_address.0, recipient, Utc::now() - window, deposit.deposit_id,
+            ).await?;
+            if distributed.saturating_add(amount) > max {
+                return Ok(Some(format!(
+                    "amount {} on top of {} already distributed exceeds the {} limit of {}",
+                    amount, distributed, name, max
+                )));
+            }
+        }
+        Ok(None)
+    }
+}
@@ -0,0 +1,84 @@
+use ethers::prelude::*;
+use ethers::utils::to_checksum;
+use std::collections::{HashMap, HashSet};
+use crate::abi::ERC20;
+use crate::alert;
+use crate::db::{self, Deposit};
+use crate::processor::Route;
+
+// A token balance a destination bridge pays distributions from: (destination chain, token)
+pub type LiquidityKey = (&'static str, H160);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Condition {
+    BelowThreshold,
+    QueueNotCovered,
+}
+
+// Tracks the ERC-20 balance of every destination bridge against the distributions
+// waiting on it. Each alert is raised once when its condition starts and logged
+// again when it clears, instead of on every pass.
+#[derive(Default)]
+pub struct LiquidityMonitor {
+    active: HashSet<(LiquidityKey, Condition)>,
+}
+
+impl LiquidityMonitor {
+    // Read every route's destination balance and return what is left to distribute this
+    // pass, after the amounts of distributions already in flight (`reserved`).
+    // A balance that cannot be read counts as zero, holding the route until the next pass.
+    pub async fn check(
+        &mut self,
+        routes: &[Route],
+        pending: &[Deposit],
+        reserved: &HashMap<LiquidityKey, U256>,
+    ) -> HashMap<LiquidityKey, U256> {
+        let mut available = HashMap::new();
+        for route in routes {
+            let destination = &route.destination;
+            let key = (destination.chain_id, route.destination_token);
+            if available.contains_key(&key) {
+                continue;
+            }
+
+            let token = ERC20::new(route.destination_token, destination.contract.client());
+            let balance = match token.balance_of(destination.contract.address()).call().await {
+                Ok(balance) => balance,
+                Err(e) => {
+                    eprintln!("Failed to read {} bridge balance of token {}: {}. Holding its distributions",
+                        destination.name, to_checksum(&route.destination_token, None), e);
+                    available.insert(key, U256::zero());
+                    continue;
+                }
+            };
+            let free = balance.saturating_sub(reserved.get(&key).copied().unwrap_or_default());
+            available.insert(key, free);
+
+            let queued = routes.iter()
+                .filter(|other| (other.destination.chain_id, other.destination_token) == key)
+                .flat_map(|other| pending.iter().filter(move |deposit| other.matches(deposit)).map(move |deposit| (other, deposit)))
+                .filter_map(|(other, deposit)| db::from_numeric(&deposit.amount).and_then(|amount| other.convert(amount)))
+                .map(|(amount, _)| amount)
+                .fold(U256::zero(), |total, amount| total.saturating_add(amount));
+
+            let below_threshold = !route.low_liquidity_threshold.is_zero() && balance < route.low_liquidity_threshold;
+            self.update(key, Condition::BelowThreshold, below_threshold, || format!(
+                "{} bridge balance of token {} is {}, below the low-liquidity threshold of {}",
+                destination.name, to_checksum(&route.destination_token, None), balance, route.low_liquidity_threshold
+            )).await;
+            self.update(key, Condition::QueueNotCovered, queued > free, || format!(
+                "{} bridge cannot cover pending distributions of token {}: {} queued, {} available",
+                destination.name, to_checksum(&route.destination_token, None), queued, free
+            )).await;
+        }
+        available
+    }
+
+    async fn update(&mut self, key: LiquidityKey, condition: Condition, holds: bool, message: impl FnOnce() -> String) {
+        if holds && self.active.insert((key, condition)) {
+            alert::raise(&message()).await;
+        } else if !holds && self.active.remove(&(key, condition)) {
+            println!("Liquidity of token {} on {} recovered ({:?} cleared)", to_checksum(&key.1, None), key.0, condition);
+        }
+    }
+}
@@ -0,0 +1,261 @@
+use chrono::Utc;
+use ethers::prelude::*;
+use sqlx::PgPool;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::time::{sleep, Duration};
+use crate::abi::{DepositFilter, TokenBridge};
+use crate::alert;
+use crate::bridge::{self, LOG_CHUNK_BLOCKS};
+use crate::db::{self, DbAddress, Deposit};
+use crate::shutdown::{Shutdown, Stats};
+use crate::updates::Updates;
+
+// A subscription sends all logs of a block back to back; once none arrived for this long
+// the buffered block is considered complete and written
+const BLOCK_IDLE: Duration = Duration::from_millis(500);
+
+// Attempts at writing a block or scanning a range before giving up on it for now
+const WRITE_ATTEMPTS: u32 = 3;
+
+// The cursor trails the newest header by this many blocks, since a load-balanced RPC endpoint
+// can answer eth_getLogs from a node that has not imported the newest blocks yet
+const CONFIRM_LAG_BLOCKS: u64 = 2;
+
+// Source bridge whose Deposit events are indexed
+pub struct Listener {
+    pub name: &'static str,
+    pub chain_id: &'static str,
+    pub provider: Arc<Provider<Ws>>,
+    pub bridge: Address,
+    pub token: Address,
+    // First block not yet scanned: the block after the cursor, or the bridge deployment
+    pub start_block: u64,
+    pub updates: Updates,
+}
+
+// Decoded Deposit logs of one block, written as soon as the block is complete
+struct BlockBatch {
+    number: u64,
+    hash: H256,
+    events: Vec<(DepositFilter, LogMeta)>,
+}
+
+impl Listener {
+    pub async fn run(self, pool: PgPool, stats: Arc<Stats>, mut shutdown: Shutdown) {
+        let contract = TokenBridge::new(self.bridge, self.provider.clone());
+        let filter = contract.deposit_filter().filter.topic1(self.token);
+
+        // Subscribe before catching up so that no block falls between the backfill and the stream
+        let (mut logs, mut headers) = loop {
+            match tokio::try_join!(self.provider.subscribe_logs(&filter), self.provider.subscribe_blocks()) {
+                Ok(streams) => {
+                    println!("Successfully subscribed to {} logs and block headers", self.name);
+                    break streams;
+                }
+                Err(e) => {
+                    eprintln!("Failed to subscribe to {} logs: {}. Retrying in 60s...", self.name, e);
+                    if shutdown.sleep(Duration::from_secs(60)).await {
+                        return;
+                    }
+                }
+            }
+        };
+
+        // The subscription delivers deposits with low latency, but nothing orders its logs
+        // against the header stream. The cursor therefore only moves through eth_getLogs scans,
+        // each committed together with the deposits it found.
+        let mut next_block = self.start_block;
+        if !self.catch_up(&contract, &pool, &stats, &mut next_block, &shutdown).await {
+            println!("{} listener stopped", self.name);
+            return;
+        }
+
+        let mut batch: Option<BlockBatch> = None;
+        let mut failed_scans = 0;
+        loop {
+            let log = tokio::select! {
+                log = logs.next() => match log {
+                    Some(log) => log,
+                    None => break,
+                },
+                header = headers.next() => {
+                    let Some(number) = header.and_then(|header| header.number) else { break };
+                    let confirmed = number.as_u64().saturating_sub(CONFIRM_LAG_BLOCKS);
+                    if confirmed < next_block {
+                        continue;
+                    }
+                    // At most one range per header, so a listener far behind still serves live logs
+                    let to = confirmed.min(next_block + LOG_CHUNK_BLOCKS - 1);
+                    match self.scan(&contract, &pool, &stats, next_block, to).await {
+                        Ok(()) => {
+                            next_block = to + 1;
+                            failed_scans = 0;
+                        }
+                        Err(e) => {
+                            failed_scans += 1;
+                            eprintln!("Failed to scan {} blocks {} to {}: {}", self.name, next_block, to, e);
+                            if failed_scans == WRITE_ATTEMPTS {
+                                alert::raise(&format!(
+                                    "Failed to scan {} blocks {} to {} {} times in a row: {}; its cursor is not advancing",
+                                    self.name, next_block, to, failed_scans, e
+                                )).await;
+                            }
+                        }
+                    }
+                    continue;
+                }
+                _ = sleep(BLOCK_IDLE), if batch.is_some() => {
+                    if let Some(batch) = batch.take() {
+                        self.write(&pool, &stats, batch).await;
+                    }
+                    continue;
+                }
+                _ = shutdown.requested() => break,
+            };
+            // Logs without a full position (pending or removed) cannot be addressed; duplicates
+            // (e.g. after a resubscription) are rejected by the database on (chain, tx hash, log index)
+            if log.transaction_hash.is_none() || log.log_index.is_none() || log.block_hash.is_none()
+                || log.block_number.is_none() || log.transaction_index.is_none() || log.removed == Some(true)
+            {
+                eprintln!("Skipping {} event with no block, transaction or log position at {}", self.name, Utc::now());
+                continue;
+            }
+            let meta = LogMeta::from(&log);
+
+            // A log from another block means the buffered one is complete
+            if batch.as_ref().is_some_and(|batch| batch.number != meta.block_number.as_u64()) {
+                if let Some(batch) = batch.take() {
+                    self.write(&pool, &stats, batch).await;
+                }
+            }
+            let batch = batch.get_or_insert_with(|| BlockBatch {
+                number: meta.block_number.as_u64(),
+                hash: meta.block_hash,
+                events: Vec::new(),
+            });
+
+            // An undecodable log is left to the range scan, which fails on it and holds the cursor
+            match contract.decode_event::<DepositFilter>("Deposit", log.topics, log.data) {
+                Ok(event) => {
+                    println!(
+                        "Decoded {} Deposit event at {}: token {} from {} to {} amount {} nonce {}",
+                        self.name, Utc::now(), DbAddress(event.token), DbAddress(event.from), DbAddress(event.to), event.amount, event.nonce
+                    );
+                    batch.events.push((event, meta));
+                }
+                Err(e) => eprintln!("Failed to decode {} Deposit event (Tx: {:?}): {}", self.name, meta.transaction_hash, e),
+            }
+        }
+        if let Some(batch) = batch.take() {
+            self.write(&pool, &stats, batch).await;
+        }
+        println!("{} listener stopped", self.name);
+    }
+
+    // Scan from `next_block` to the current head, retrying each range a few times. A range that
+    // still fails is left to the scans driven by new headers. Returns false on shutdown.
+    async fn catch_up(
+        &self,
+        contract: &TokenBridge<Provider<Ws>>,
+        pool: &PgPool,
+        stats: &Stats,
+        next_block: &mut u64,
+        shutdown: &Shutdown,
+    ) -> bool {
+        let head = match self.provider.get_block_number().await {
+            Ok(head) => head.as_u64().saturating_sub(CONFIRM_LAG_BLOCKS),
+            Err(e) => {
+                eprintln!("Failed to read the {} head to catch up from block {}: {}", self.name, next_block, e);
+                return true;
+            }
+        };
+        if *next_block <= head {
+            println!("Scanning {} blocks {} to {} for missed Deposit events", self.name, next_block, head);
+        }
+
+        while *next_block <= head {
+            if shutdown.is_requested() {
+                return false;
+            }
+            let to = (*next_block + LOG_CHUNK_BLOCKS - 1).min(head);
+            let mut attempt = 1;
+            loop {
+                match self.scan(contract, pool, stats, *next_block, to).await {
+                    Ok(()) => break,
+                    Err(e) if attempt < WRITE_ATTEMPTS => {
+                        eprintln!("Failed to scan {} blocks {} to {} (attempt {}/{}): {}",
+                            self.name, next_block, to, attempt, WRITE_ATTEMPTS, e);
+                        sleep(Duration::from_secs(attempt as u64)).await;
+                        attempt += 1;
+                    }
+                    Err(e) => {
+                        alert::raise(&format!(
+                            "Failed to scan {} blocks {} to {}: {}; its cursor is not advancing",
+                            self.name, next_block, to, e
+                        )).await;
+                        return true;
+                    }
+                }
+            }
+            *next_block = to + 1;
+        }
+        true
+    }
+
+    // Index the Deposit logs of blocks `from..=to` and advance the cursor to `to` in one transaction
+    async fn scan(
+        &self,
+        contract: &TokenBridge<Provider<Ws>>,
+        pool: &PgPool,
+        stats: &Stats,
+        from: u64,
+        to: u64,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let events = contract.deposit_filter().topic1(self.token).from_block(from).to_block(to).query_with_meta().await?;
+        let mut timestamps = HashMap::new();
+        let mut deposits = Vec::with_capacity(events.len());
+        for (event, meta) in &events {
+            let block_timestamp = match timestamps.get(&meta.block_hash) {
+                Some(timestamp) => *timestamp,
+                None => {
+                    let timestamp = bridge::block_timestamp(self.provider.as_ref(), meta.block_hash).await;
+                    timestamps.insert(meta.block_hash, timestamp);
+                    timestamp
+                }
+            };
+            deposits.push(Deposit::from_event(self.chain_id, event, meta, block_timestamp));
+        }
+        let inserted = db::insert_block_deposits(pool, self.chain_id, &deposits, Some(to as i64)).await?;
+        if !inserted.is_empty() {
+            println!("Indexed {} missed {} deposits in blocks {} to {}", inserted.len(), self.name, from, to);
+        }
+        Stats::add(&stats.deposits_indexed, inserted.len() as u64);
+        inserted.into_iter().for_each(|deposit_id| self.updates.notify(deposit_id));
+        Ok(())
+    }
+
+    // Insert a block's deposits as soon as it is complete. The cursor is left to the range scans.
+    async fn write(&self, pool: &PgPool, stats: &Stats, batch: BlockBatch) {
+        let block_timestamp = bridge::block_timestamp(self.provider.as_ref(), batch.hash).await;
+        let deposits: Vec<Deposit> = batch.events.iter()
+            .map(|(event, meta)| Deposit::from_event(self.chain_id, event, meta, block_timestamp))
+            .collect();
+
+        for attempt in 1..=WRITE_ATTEMPTS {
+            match db::insert_block_deposits(pool, self.chain_id, &deposits, None).await {
+                Ok(inserted) => {
+                    Stats::add(&stats.deposits_indexed, inserted.len() as u64);
+                    inserted.into_iter().for_each(|deposit_id| self.updates.notify(deposit_id));
+                    return;
+                }
+                Err(e) => {
+                    eprintln!("Failed to write {} deposits of {} block {} (attempt {}/{}): {}",
+                        deposits.len(), self.name, batch.number, attempt, WRITE_ATTEMPTS, e);
+                    sleep(Duration::from_secs(attempt as u64)).await;
+                }
+            }
+        }
+        eprintln!("Giving up on {} block {}; the range scan will index its deposits", self.name, batch.number);
+    }
+}
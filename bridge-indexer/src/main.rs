@@ -1,22 +1,47 @@
 use dotenv::dotenv;
 use ethers::prelude::*;
 use ethers::providers::{Provider, Ws};
-use ethers::signers::LocalWallet;
 use ethers::middleware::SignerMiddleware;
+use ethers::utils::to_checksum;
 use std::env;
 use std::sync::Arc;
 use sqlx::PgPool;
 use uuid::Uuid;
-use std::str::FromStr;
-use crate::abi::{TokenBridge, DepositFilter};
-use crate::db::Deposit;
-use hex;
-use std::collections::HashSet;
-use chrono::Utc;
-use tokio::time::{sleep, Duration};
+use crate::abi::{ERC20, TokenBridge};
+use crate::approval::ApprovalPolicy;
+use crate::db::DbAddress;
+use crate::decimals::DustPolicy;
+use crate::fee::FeeModel;
+use crate::shutdown::Stats;
+use crate::gas::GasPolicy;
+use crate::limits::RateLimits;
+use crate::listener::Listener;
+use crate::processor::{Destination, Processor, Route};
+use crate::screening::Screening;
+use crate::signer::Client;
+use tokio::time::Duration;
 
 mod abi;
+mod alert;
+mod api;
+mod approval;
+mod bridge;
+mod config;
 mod db;
+mod decimals;
+mod fee;
+mod gaps;
+mod gas;
+mod limits;
+mod listener;
+mod liquidity;
+mod nonce;
+mod processor;
+mod reconcile;
+mod screening;
+mod shutdown;
+mod signer;
+mod updates;
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
@@ -39,16 +64,29 @@ async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
         .parse()?;
     let holesky_chain_id = "holesky";
     let target_chain_id = "base-sepolia";
+    let holesky_evm_chain_id = 17000u64; // Holesky Chain ID
+    let target_evm_chain_id = 84532u64; // Base Sepolia Chain ID
 
-    println!("Holesky Test Token: 0x{}", hex::encode(holesky_test_token.as_bytes()));
-    println!("Target Chain Test Token: 0x{}", hex::encode(target_chain_test_token.as_bytes()));
+    println!("Holesky Test Token: {}", to_checksum(&holesky_test_token, None));
+    println!("Target Chain Test Token: {}", to_checksum(&target_chain_test_token, None));
 
     let database_url = env::var("DATABASE_URL").expect("DATABASE_URL must be set");
     let pool = PgPool::connect(&database_url).await?;
     db::init_db(&pool).await?;
 
-    let holesky_start_block = 3519562; // Deployment block for Holesky
-    let target_chain_start_block = db::get_last_processed_block(&pool, target_chain_id).await.unwrap_or(0);
+    // Operator Commands: `held` / `awaiting-approval` list deposits waiting on an operator,
+    // `release <deposit_id>` lets a held one through, `approve <deposit_id>` signs an approval
+    // and `fees` prints fee totals per token
+    let args: Vec<String> = env::args().collect();
+    if matches!(args.get(1).map(String::as_str), Some("held" | "release" | "awaiting-approval" | "approve" | "fees")) {
+        operator_command(&pool, &args[1..]).await?;
+        return Ok(());
+    }
+
+    let holesky_deploy_block = bridge_deploy_block("HOLESKY")?.unwrap_or(3519562); // Deployment block for Holesky
+    // Scanning a chain from genesis would take thousands of eth_getLogs calls, so there is no
+    // default; it is only needed where the database has nothing to resume from
+    let target_chain_deploy_block = bridge_deploy_block("TARGET_CHAIN")?;
 
     // Provider and Client Initialization
     let holesky_provider = Provider::<Ws>::connect(holesky_ws_url).await?;
@@ -56,22 +94,105 @@ async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
     let holesky_provider = Arc::new(holesky_provider);
     let target_chain_provider = Arc::new(target_chain_provider);
 
-    let wallet_holesky = env::var("PRIVATE_KEY")
-        .expect("PRIVATE_KEY must be set")
-        .parse::<LocalWallet>()?
-        .with_chain_id(17000u64); // Holesky Chain ID
-
-    let wallet_base_sepolia = env::var("PRIVATE_KEY")
-        .expect("PRIVATE_KEY must be set")
-        .parse::<LocalWallet>()?
-        .with_chain_id(84532u64); // Base Sepolia Chain ID
+    let wallet_holesky = signer::from_env("HOLESKY", holesky_evm_chain_id)?;
+    let wallet_base_sepolia = signer::from_env("TARGET_CHAIN", target_evm_chain_id)?;
+    println!("Holesky distributor address: {}", to_checksum(&wallet_holesky.address(), None));
+    println!("Base Sepolia distributor address: {}", to_checksum(&wallet_base_sepolia.address(), None));
+    if wallet_holesky.address() == wallet_base_sepolia.address() {
+        println!("Warning: the same distributor key is used on Holesky and Base Sepolia; set HOLESKY_* and TARGET_CHAIN_* signer settings to separate them");
+    }
 
     let holesky_client = Arc::new(SignerMiddleware::new(holesky_provider.clone(), wallet_holesky));
     let target_client = Arc::new(SignerMiddleware::new(target_chain_provider.clone(), wallet_base_sepolia));
 
-    // Unpause contracts at startup
-    println!("Starting indexer and unpausing contracts...");
-    unpause_contracts(&holesky_client, &target_client, holesky_bridge_address, target_chain_bridge_address).await;
+    // Reconciliation: `reconcile [report_path]` compares the database with both chains
+    if args.get(1).map(String::as_str) == Some("reconcile") {
+        let deployments = [
+            reconcile::Deployment {
+                name: "Holesky",
+                chain_id: holesky_chain_id,
+                evm_chain_id: holesky_evm_chain_id,
+                contract: TokenBridge::new(holesky_bridge_address, holesky_client.clone()),
+                token: holesky_test_token,
+                deploy_block: holesky_deploy_block,
+                destination_chain_id: target_chain_id,
+            },
+            reconcile::Deployment {
+                name: "Base Sepolia",
+                chain_id: target_chain_id,
+                evm_chain_id: target_evm_chain_id,
+                contract: TokenBridge::new(target_chain_bridge_address, target_client.clone()),
+                token: target_chain_test_token,
+                deploy_block: target_chain_deploy_block
+                    .ok_or("TARGET_CHAIN_BRIDGE_DEPLOY_BLOCK must be set to reconcile Base Sepolia")?,
+                destination_chain_id: holesky_chain_id,
+            },
+        ];
+        let report = reconcile::run(&pool, &deployments).await?;
+        let path = args.get(2).map(String::as_str).unwrap_or("reconciliation_report.json");
+        std::fs::write(path, serde_json::to_string_pretty(&report)?)?;
+        let count = report["discrepancies"].as_array().map(Vec::len).unwrap_or_default();
+        println!("Reconciliation found {} discrepancies; report written to {}", count, path);
+        if count > 0 {
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
+    // Admin Commands: `pause` / `unpause` [holesky|base-sepolia|all]
+    if let Some(command) = args.get(1) {
+        let paused = match command.as_str() {
+            "pause" => true,
+            "unpause" => false,
+            _ => {
+                eprintln!("Unknown command: {}. Usage: bridge-indexer [pause|unpause] [holesky|base-sepolia|all] | reconcile [report_path] | held | awaiting-approval | release <deposit_id> | approve <deposit_id> | fees", command);
+                std::process::exit(2);
+            }
+        };
+        let target = args.get(2).map(String::as_str).unwrap_or("all");
+        if target != holesky_chain_id && target != target_chain_id && target != "all" {
+            eprintln!("Unknown chain: {}. Expected holesky, base-sepolia or all", target);
+            std::process::exit(2);
+        }
+        if target == holesky_chain_id || target == "all" {
+            set_contract_paused(&holesky_client, holesky_bridge_address, "Holesky", paused).await?;
+        }
+        if target == target_chain_id || target == "all" {
+            set_contract_paused(&target_client, target_chain_bridge_address, "Base Sepolia", paused).await?;
+        }
+        return Ok(());
+    }
+
+    // Check each distributor against `onlyDistributor` and report paused state at startup;
+    // pausing is an explicit admin operation
+    println!("Starting indexer...");
+    for (name, client, bridge_address) in [
+        ("Holesky", &holesky_client, holesky_bridge_address),
+        ("Base Sepolia", &target_client, target_chain_bridge_address),
+    ] {
+        let contract = TokenBridge::new(bridge_address, client.clone());
+        match contract.owner().call().await {
+            Ok(owner) if owner == client.address() => {
+                println!("{} distributor {} is the bridge owner", name, to_checksum(&owner, None))
+            }
+            Ok(owner) => eprintln!(
+                "Warning: {} distributor {} is not the bridge owner {}; distributions to {} will revert with \"Bridge: not distributor\"",
+                name, to_checksum(&client.address(), None), to_checksum(&owner, None), name
+            ),
+            Err(e) => eprintln!("Failed to read owner of {} bridge: {}", name, e),
+        }
+        match bridge::is_paused(&contract).await {
+            Ok(true) => println!("{} bridge is paused, distributions to it will be held", name),
+            Ok(false) => println!("{} bridge is active", name),
+            Err(e) => eprintln!("Failed to read paused state of {} bridge: {}", name, e),
+        }
+    }
+
+    let shutdown_timeout_secs: u64 = env::var("SHUTDOWN_TIMEOUT_SECS")
+        .unwrap_or_else(|_| "30".to_string())
+        .parse()?;
+    let (shutdown_tx, shutdown) = shutdown::channel();
+    let stats = Arc::new(Stats::default());
 
     let holesky_pool = pool.clone();
     let target_chain_pool = pool.clone();
@@ -80,157 +201,41 @@ async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
     let holesky_provider_clone = holesky_provider.clone();
     let target_chain_provider_clone = target_chain_provider.clone();
 
-    // Holesky Event Listener
-    let _holesky_handle = tokio::spawn({
-        let holesky_provider = holesky_provider_clone.clone();
-        async move {
-            let holesky_contract = TokenBridge::new(holesky_bridge_address, holesky_provider.clone());
-            let event = holesky_contract.deposit_filter();
-            let filter = event.filter
-                .from_block(holesky_start_block)
-                .topic1(holesky_test_token);
-            println!("Subscribing to Deposit events from block {} on Holesky", holesky_start_block);
-
-            let mut stream = loop {
-                match holesky_provider.subscribe_logs(&filter).await {
-                    Ok(stream) => {
-                        println!("Successfully subscribed to Holesky logs");
-                        break stream;
-                    }
-                    Err(e) => {
-                        eprintln!("Failed to subscribe to Holesky logs: {}. Retrying in 60s...", e);
-                        sleep(Duration::from_secs(60)).await;
-                    }
-                }
-            };
-
-            let mut processed_tx_hashes: HashSet<String> = HashSet::new();
+    // Deposit status changes, streamed to API subscribers
+    let (updates, update_rx) = updates::channel();
 
-            while let Some(log) = stream.next().await {
-                let transaction_hash = match log.transaction_hash {
-                    Some(hash) => format!("{:?}", hash),
-                    None => {
-                        eprintln!("Skipping Holesky event with no transaction hash at {}", Utc::now());
-                        continue;
-                    }
-                };
-
-                if processed_tx_hashes.contains(&transaction_hash) {
-                    println!("Skipping duplicate event - Tx Hash: {}", transaction_hash);
-                    continue;
-                }
+    let holesky_start_block = start_block(&pool, holesky_chain_id, "HOLESKY", Some(holesky_deploy_block)).await?;
+    let target_chain_start_block = start_block(&pool, target_chain_id, "TARGET_CHAIN", target_chain_deploy_block).await?;
 
-                match holesky_contract.decode_event::<DepositFilter>("Deposit", log.topics, log.data) {
-                    Ok(event) => {
-                        println!("Decoded Holesky Deposit event at {}: {:?}", Utc::now(), event);
-                        let block_number = log.block_number.unwrap_or_default().as_u64() as i64;
-                        let amount_str = event.amount.to_string();
-                        let nonce_str = event.nonce.to_string();
-                        let deposit = Deposit {
-                            deposit_id: Uuid::new_v4(),
-                            chain_id: holesky_chain_id.to_string(),
-                            transaction_hash: transaction_hash.clone(),
-                            block_number,
-                            token_address: format!("0x{}", hex::encode(event.token.as_bytes())),
-                            from_address: format!("0x{}", hex::encode(event.from.as_bytes())),
-                            to_address: format!("0x{}", hex::encode(event.to.as_bytes())),
-                            amount: amount_str,
-                            nonce: nonce_str,
-                            processed: Some(false),
-                            finality_confirmed: Some(false),
-                            created_at: None,
-                            updated_at: None,
-                        };
-                        if let Err(e) = db::insert_deposit(&holesky_pool, &deposit).await {
-                            eprintln!("Failed to insert deposit (Tx: {}) into DB: {}", transaction_hash, e);
-                        }
-                        if let Err(e) = db::update_last_processed_block(&holesky_pool, holesky_chain_id, block_number).await {
-                            eprintln!("Failed to update last processed block ({}) for Holesky: {}", block_number, e);
-                        }
-                        processed_tx_hashes.insert(transaction_hash);
-                    }
-                    Err(e) => eprintln!("Failed to decode Holesky Deposit event (Tx: {}): {}", transaction_hash, e),
-                }
-            }
-        }
-    });
+    // Holesky Event Listener
+    let holesky_listener = Listener {
+        name: "Holesky",
+        chain_id: holesky_chain_id,
+        provider: holesky_provider_clone.clone(),
+        bridge: holesky_bridge_address,
+        token: holesky_test_token,
+        start_block: holesky_start_block,
+        updates: updates.clone(),
+    };
+    let holesky_handle = tokio::spawn(holesky_listener.run(holesky_pool, stats.clone(), shutdown.clone()));
 
     // Base Sepolia Event Listener
-    let _target_chain_handle = tokio::spawn({
-        let target_chain_provider = target_chain_provider_clone.clone();
-        async move {
-            let target_chain_contract = TokenBridge::new(target_chain_bridge_address, target_chain_provider.clone());
-            let event = target_chain_contract.deposit_filter();
-            let filter = event.filter
-                .from_block(target_chain_start_block)
-                .topic1(target_chain_test_token);
-
-            let mut stream = loop {
-                match target_chain_provider.subscribe_logs(&filter).await {
-                    Ok(stream) => {
-                        println!("Successfully subscribed to Base Sepolia logs");
-                        break stream;
-                    }
-                    Err(e) => {
-                        eprintln!("Failed to subscribe to Base Sepolia logs: {}. Retrying in 60s...", e);
-                        sleep(Duration::from_secs(60)).await;
-                    }
-                }
-            };
-
-            let mut processed_tx_hashes: HashSet<String> = HashSet::new();
-
-            while let Some(log) = stream.next().await {
-                let transaction_hash = match log.transaction_hash {
-                    Some(hash) => format!("{:?}", hash),
-                    None => {
-                        eprintln!("Skipping Base Sepolia event with no transaction hash at {}", Utc::now());
-                        continue;
-                    }
-                };
-
-                if processed_tx_hashes.contains(&transaction_hash) {
-                    println!("Skipping duplicate event - Tx Hash: {}", transaction_hash);
-                    continue;
-                }
-
-                match target_chain_contract.decode_event::<DepositFilter>("Deposit", log.topics, log.data) {
-                    Ok(event) => {
-                        println!("Target Chain Deposit event at {}: {:?}", Utc::now(), event);
-                        let block_number = log.block_number.unwrap_or_default().as_u64() as i64;
-                        let amount_str = event.amount.to_string();
-                        let nonce_str = event.nonce.to_string();
-                        let deposit = Deposit {
-                            deposit_id: Uuid::new_v4(),
-                            chain_id: target_chain_id.to_string(),
-                            transaction_hash: transaction_hash.clone(),
-                            block_number,
-                            token_address: format!("0x{}", hex::encode(event.token.as_bytes())),
-                            from_address: format!("0x{}", hex::encode(event.from.as_bytes())),
-                            to_address: format!("0x{}", hex::encode(event.to.as_bytes())),
-                            amount: amount_str,
-                            nonce: nonce_str,
-                            processed: Some(false),
-                            finality_confirmed: Some(false),
-                            created_at: None,
-                            updated_at: None,
-                        };
-                        if let Err(e) = db::insert_deposit(&target_chain_pool, &deposit).await {
-                            eprintln!("Failed to insert deposit (Tx: {}) into DB: {}", transaction_hash, e);
-                        }
-                        if let Err(e) = db::update_last_processed_block(&target_chain_pool, target_chain_id, block_number).await {
-                            eprintln!("Failed to update last processed block ({}) for Base Sepolia: {}", block_number, e);
-                        }
-                        processed_tx_hashes.insert(transaction_hash);
-                    }
-                    Err(e) => eprintln!("Failed to decode Base Sepolia Deposit event (Tx: {}): {}", transaction_hash, e),
-                }
-            }
-        }
-    });
+    let target_chain_listener = Listener {
+        name: "Base Sepolia",
+        chain_id: target_chain_id,
+        provider: target_chain_provider_clone.clone(),
+        bridge: target_chain_bridge_address,
+        token: target_chain_test_token,
+        start_block: target_chain_start_block,
+        updates: updates.clone(),
+    };
+    let target_chain_handle = tokio::spawn(target_chain_listener.run(target_chain_pool, stats.clone(), shutdown.clone()));
 
     // Finality Confirmation Loop
-    let _confirmation_handle = tokio::spawn(async move {
+    let mut confirmation_shutdown = shutdown.clone();
+    let confirmation_updates = updates.clone();
+    let confirmation_handle = tokio::spawn(async move {
+        let shutdown = &mut confirmation_shutdown;
         let confirmation_blocks = 12;
 
         loop {
@@ -238,7 +243,9 @@ async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
                 Ok(block) => block.as_u64() as i64,
                 Err(e) => {
                     eprintln!("Failed to get Holesky block number: {}. Retrying in 60s...", e);
-                    sleep(Duration::from_secs(60)).await;
+                    if shutdown.sleep(Duration::from_secs(60)).await {
+                        break;
+                    }
                     continue;
                 }
             };
@@ -246,30 +253,21 @@ async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
                 Ok(block) => block.as_u64() as i64,
                 Err(e) => {
                     eprintln!("Failed to get Base Sepolia block number: {}. Retrying in 60s...", e);
-                    sleep(Duration::from_secs(60)).await;
+                    if shutdown.sleep(Duration::from_secs(60)).await {
+                        break;
+                    }
                     continue;
                 }
             };
 
-            match sqlx::query(
-                r#"
-                UPDATE deposits
-                SET finality_confirmed = TRUE, updated_at = CURRENT_TIMESTAMP
-                WHERE chain_id = $1 AND block_number <= $2 AND finality_confirmed = FALSE
-                "#,
-            )
-            .bind(holesky_chain_id)
-            .bind(holesky_block - confirmation_blocks)
-            .execute(&confirmation_pool)
-            .await
-            {
-                Ok(result) => {
-                    let rows_affected = result.rows_affected();
+            match db::confirm_finality(&confirmation_pool, holesky_chain_id, holesky_block - confirmation_blocks).await {
+                Ok(confirmed) => {
                     println!(
                         "Updated finality for {} deposits on Holesky up to block {}",
-                        rows_affected,
+                        confirmed.len(),
                         holesky_block - confirmation_blocks
                     );
+                    confirmed.into_iter().for_each(|deposit_id| confirmation_updates.notify(deposit_id));
                 }
                 Err(e) => {
                     eprintln!("Failed to update finality for Holesky deposits up to block {}: {}", 
@@ -277,25 +275,14 @@ async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
                 }
             }
 
-            match sqlx::query(
-                r#"
-                UPDATE deposits
-                SET finality_confirmed = TRUE, updated_at = CURRENT_TIMESTAMP
-                WHERE chain_id = $1 AND block_number <= $2 AND finality_confirmed = FALSE
-                "#,
-            )
-            .bind(target_chain_id)
-            .bind(target_block - confirmation_blocks)
-            .execute(&confirmation_pool)
-            .await
-            {
-                Ok(result) => {
-                    let rows_affected = result.rows_affected();
+            match db::confirm_finality(&confirmation_pool, target_chain_id, target_block - confirmation_blocks).await {
+                Ok(confirmed) => {
                     println!(
                         "Updated finality for {} deposits on Base Sepolia up to block {}",
-                        rows_affected,
+                        confirmed.len(),
                         target_block - confirmation_blocks
                     );
+                    confirmed.into_iter().for_each(|deposit_id| confirmation_updates.notify(deposit_id));
                 }
                 Err(e) => {
                     eprintln!("Failed to update finality for Base Sepolia deposits up to block {}: {}", 
@@ -303,223 +290,284 @@ async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
                 }
             }
 
-            sleep(Duration::from_secs(60)).await;
+            if shutdown.sleep(Duration::from_secs(60)).await {
+                break;
+            }
         }
+        println!("Finality confirmation loop stopped");
     });
 
-    // Transaction Processing Loop
-    let holesky_client_clone = holesky_client.clone();
-    let target_client_clone = target_client.clone();
-    let _tx_handle = tokio::spawn(async move {
-        let holesky_contract = TokenBridge::new(holesky_bridge_address, holesky_client_clone.clone());
-        let target_contract = TokenBridge::new(target_chain_bridge_address, target_client_clone.clone());
-
-        loop {
-            println!("Checking for unprocessed deposits at {}", Utc::now());
-            let deposits = match db::get_unprocessed_deposits(&tx_pool).await {
-                Ok(deposits) => deposits,
-                Err(e) => {
-                    eprintln!("Failed to fetch unprocessed deposits: {}. Retrying in 60s...", e);
-                    sleep(Duration::from_secs(60)).await;
-                    continue;
-                }
-            };
+    // Gap Detection Loop: backfills deposits whose nonces were skipped
+    let gap_check_interval_secs: u64 = env::var("GAP_CHECK_INTERVAL_SECS")
+        .unwrap_or_else(|_| "300".to_string())
+        .parse()?;
+    let gap_sources = vec![
+        gaps::SourceBridge {
+            name: "Holesky",
+            chain_id: holesky_chain_id,
+            contract: TokenBridge::new(holesky_bridge_address, holesky_provider_clone.clone()),
+            token: holesky_test_token,
+            deploy_block: Some(holesky_deploy_block),
+            updates: updates.clone(),
+        },
+        gaps::SourceBridge {
+            name: "Base Sepolia",
+            chain_id: target_chain_id,
+            contract: TokenBridge::new(target_chain_bridge_address, target_chain_provider_clone.clone()),
+            token: target_chain_test_token,
+            deploy_block: target_chain_deploy_block,
+            updates: updates.clone(),
+        },
+    ];
+    let gap_handle = tokio::spawn(gaps::run(
+        pool.clone(), gap_sources, stats.clone(), Duration::from_secs(gap_check_interval_secs.max(1)), shutdown.clone(),
+    ));
 
-            if deposits.is_empty() {
-                println!("No unprocessed deposits found at {}", Utc::now());
-                sleep(Duration::from_secs(60)).await;
-                continue;
+    // Transaction Processing Loop
+    let max_in_flight: usize = env::var("MAX_IN_FLIGHT_DISTRIBUTIONS")
+        .unwrap_or_else(|_| "8".to_string())
+        .parse()?;
+    let holesky_destination = Arc::new(Destination::new(
+        "Holesky", holesky_chain_id, holesky_bridge_address, holesky_client.clone(), GasPolicy::from_env("HOLESKY")?,
+    ));
+    let target_destination = Arc::new(Destination::new(
+        "Base Sepolia", target_chain_id, target_chain_bridge_address, target_client.clone(), GasPolicy::from_env("TARGET_CHAIN")?,
+    ));
+    // Amounts are converted between the tokens' decimals when they differ
+    let holesky_token_decimals = ERC20::new(holesky_test_token, holesky_client.clone()).decimals().call().await?;
+    let target_chain_token_decimals = ERC20::new(target_chain_test_token, target_client.clone()).decimals().call().await?;
+    println!("Token decimals: {} on Holesky, {} on Base Sepolia", holesky_token_decimals, target_chain_token_decimals);
+    let routes = vec![
+        Route {
+            source_chain_id: holesky_chain_id,
+            source_evm_chain_id: holesky_evm_chain_id,
+            source_bridge: holesky_bridge_address,
+            source_token: holesky_test_token,
+            destination: target_destination,
+            destination_token: target_chain_test_token,
+            source_decimals: holesky_token_decimals,
+            destination_decimals: target_chain_token_decimals,
+            dust_policy: DustPolicy::from_env("TARGET_CHAIN")?,
+            fee_model: FeeModel::from_env("TARGET_CHAIN")?,
+            low_liquidity_threshold: config::amount_var("TARGET_CHAIN", "LOW_LIQUIDITY_THRESHOLD")?.unwrap_or_default(),
+            limits: RateLimits::from_env("TARGET_CHAIN")?,
+            approval_threshold: config::amount_var("TARGET_CHAIN", "APPROVAL_THRESHOLD")?,
+        },
+        Route {
+            source_chain_id: target_chain_id,
+            source_evm_chain_id: target_evm_chain_id,
+            source_bridge: target_chain_bridge_address,
+            source_token: target_chain_test_token,
+            destination: holesky_destination,
+            destination_token: holesky_test_token,
+            source_decimals: target_chain_token_decimals,
+            destination_decimals: holesky_token_decimals,
+            dust_policy: DustPolicy::from_env("HOLESKY")?,
+            fee_model: FeeModel::from_env("HOLESKY")?,
+            low_liquidity_threshold: config::amount_var("HOLESKY", "LOW_LIQUIDITY_THRESHOLD")?.unwrap_or_default(),
+            limits: RateLimits::from_env("HOLESKY")?,
+            approval_threshold: config::amount_var("HOLESKY", "APPROVAL_THRESHOLD")?,
+        },
+    ];
+    let max_attempts: i32 = env::var("MAX_DISTRIBUTION_ATTEMPTS")
+        .unwrap_or_else(|_| "3".to_string())
+        .parse()?;
+    let approvals = ApprovalPolicy::from_env()?;
+    if routes.iter().any(|route| route.approval_threshold.is_some()) && !approvals.has_approvers() {
+        return Err(format!("APPROVAL_THRESHOLD is set but APPROVERS lists fewer than {} operators", approvals.required()).into());
+    }
+    for distributor in [holesky_client.address(), target_client.address()] {
+        if approvals.is_approver(distributor) {
+            return Err(format!("APPROVERS lists the distributor address {}; approvers must use separate keys", to_checksum(&distributor, None)).into());
+        }
+    }
+    let screening = Screening::from_env()?;
+    let processor = Processor::new(tx_pool, routes, stats.clone(), max_in_flight, max_attempts, approvals, screening, updates.clone());
+    let tx_handle = tokio::spawn(processor.run(shutdown.clone()));
+
+    // HTTP API for deposit lookups, served on API_LISTEN_ADDR (unset disables it)
+    let api_handle = match env::var("API_LISTEN_ADDR") {
+        Ok(addr) => {
+            let listener = tokio::net::TcpListener::bind(addr.trim()).await?;
+            Some(tokio::spawn(api::serve(listener, pool.clone(), update_rx, shutdown.clone())))
+        }
+        Err(_) => {
+            drop(update_rx); // Nothing streams updates, so stop queueing them
+            None
+        }
+    };
+
+    // Shutdown Handling: stop all tasks cooperatively, bounded by SHUTDOWN_TIMEOUT_SECS
+    let signal = shutdown::wait_for_signal().await?;
+    println!("Received {}. Shutting down (deadline {}s)...", signal, shutdown_timeout_secs);
+    let _ = shutdown_tx.send(true);
+
+    let deadline = tokio::time::Instant::now() + Duration::from_secs(shutdown_timeout_secs);
+    let mut unfinished_tasks = 0;
+    let mut handles = vec![
+        ("Holesky listener", holesky_handle),
+        ("Base Sepolia listener", target_chain_handle),
+        ("finality confirmation loop", confirmation_handle),
+        ("gap detection loop", gap_handle),
+        ("transaction processor", tx_handle),
+    ];
+    handles.extend(api_handle.map(|handle| ("API server", handle)));
+    for (name, handle) in handles {
+        let abort_handle = handle.abort_handle();
+        match tokio::time::timeout_at(deadline, handle).await {
+            Ok(Ok(())) => {}
+            Ok(Err(e)) => eprintln!("The {} exited abnormally: {}", name, e),
+            Err(_) => {
+                eprintln!("The {} did not stop before the deadline, aborting it", name);
+                abort_handle.abort();
+                unfinished_tasks += 1;
             }
+        }
+    }
 
-            println!("Found {} unprocessed deposits at {}", deposits.len(), Utc::now());
-
-            for deposit in deposits {
-                println!("Processing deposit at {}: {:?}", Utc::now(), deposit);
-                let deposit_token = match H160::from_str(&deposit.token_address) {
-                    Ok(addr) => addr,
-                    Err(e) => {
-                        eprintln!("Failed to parse token address for deposit {}: {}", deposit.deposit_id, e);
-                        continue;
-                    }
-                };
+    let pending_deposits = db::count_unprocessed_deposits(&pool).await.ok();
+    pool.close().await;
+    stats.print_summary(unfinished_tasks, pending_deposits);
+    Ok(())
+}
 
-                let (contract, token_to_distribute) = if deposit.chain_id == holesky_chain_id {
-                    if deposit_token != holesky_test_token {
-                        eprintln!("Unexpected token for Holesky deposit {}: {}", deposit.deposit_id, deposit.token_address);
-                        continue;
-                    }
-                    (&target_contract, target_chain_test_token)
-                } else if deposit.chain_id == target_chain_id {
-                    if deposit_token != target_chain_test_token {
-                        eprintln!("Unexpected token for Base Sepolia deposit {}: {}", deposit.deposit_id, deposit.token_address);
-                        continue;
-                    }
-                    (&holesky_contract, holesky_test_token)
-                } else {
-                    eprintln!("Unknown chain_id for deposit {}: {}", deposit.deposit_id, deposit.chain_id);
-                    continue;
-                };
+// Pause or Unpause a Bridge Contract (admin command)
+async fn set_contract_paused(
+    client: &Arc<Client>,
+    bridge_address: H160,
+    chain_name: &str,
+    paused: bool,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let contract = TokenBridge::new(bridge_address, client.clone());
+    let action = if paused { "pause" } else { "unpause" };
+    let call = if paused { contract.pause() } else { contract.unpause() };
+    let pending_tx = call.send().await?;
+    println!("Sent {} transaction for {} contract: {:?}", action, chain_name, pending_tx.tx_hash());
+    match pending_tx.await? {
+        Some(receipt) if receipt.status == Some(1.into()) => {
+            println!("{} contract {}d in block {:?}", chain_name, action, receipt.block_number);
+        }
+        Some(receipt) => eprintln!("{} transaction reverted for {} contract: {:?}", action, chain_name, receipt.transaction_hash),
+        None => eprintln!("Receipt not found for {} transaction on {} contract", action, chain_name),
+    }
+    Ok(())
+}
 
-                let to_address = match H160::from_str(&deposit.to_address) {
-                    Ok(addr) => addr,
-                    Err(e) => {
-                        eprintln!("Failed to parse to_address for deposit {}: {}", deposit.deposit_id, e);
-                        continue;
-                    }
-                };
+// {PREFIX}_BRIDGE_DEPLOY_BLOCK: where a listener starts on a fresh database, and where gap
+// backfills and reconciliation start scanning a bridge's events
+fn bridge_deploy_block(prefix: &str) -> Result<Option<u64>, Box<dyn std::error::Error + Send + Sync>> {
+    match config::chain_var(prefix, "BRIDGE_DEPLOY_BLOCK") {
+        Some(value) => Ok(Some(value.trim().parse()?)),
+        None => Ok(None),
+    }
+}
 
-                let amount = match U256::from_dec_str(&deposit.amount) {
-                    Ok(amount) => amount,
-                    Err(e) => {
-                        eprintln!("Failed to parse amount '{}' for deposit {}: {}", deposit.amount, deposit.deposit_id, e);
-                        continue;
-                    }
-                };
+// Listeners resume after the last block they fully scanned; a fresh database starts at the
+// bridge deployment, which must then be configured
+async fn start_block(
+    pool: &PgPool,
+    chain_id: &str,
+    prefix: &str,
+    deploy_block: Option<u64>,
+) -> Result<u64, Box<dyn std::error::Error + Send + Sync>> {
+    match (db::get_last_processed_block(pool, chain_id).await?, deploy_block) {
+        (Some(cursor), deploy_block) => Ok((cursor as u64 + 1).max(deploy_block.unwrap_or_default())),
+        (None, Some(deploy_block)) => Ok(deploy_block),
+        (None, None) => Err(format!("{}_BRIDGE_DEPLOY_BLOCK must be set to index {} on a fresh database", prefix, chain_id).into()),
+    }
+}
 
-                let nonce = match U256::from_dec_str(&deposit.nonce) {
-                    Ok(nonce) => nonce,
-                    Err(e) => {
-                        eprintln!("Failed to parse nonce '{}' for deposit {}: {}", deposit.nonce, deposit.deposit_id, e);
-                        continue;
-                    }
-                };
-
-                let is_processed = match contract.processed_deposits(nonce).call().await {
-                    Ok(processed) => processed,
-                    Err(e) => {
-                        eprintln!("Failed to check processedDeposits for deposit {} (nonce {}): {}", 
-                            deposit.deposit_id, nonce, e);
-                        true // Assume processed to avoid repeated failures
-                    }
-                };
-                if is_processed {
-                    println!("Deposit with nonce {} already processed for deposit {} at {}", 
-                        nonce, deposit.deposit_id, Utc::now());
-                    if let Err(e) = db::update_deposit_status(&tx_pool, deposit.deposit_id, true, true).await {
-                        eprintln!("Failed to update deposit status for deposit {}: {}", deposit.deposit_id, e);
-                    }
-                    continue;
-                };
-
-                let distribution_chain = if deposit.chain_id == holesky_chain_id {
-                    target_chain_id
-                } else {
-                    holesky_chain_id
-                };
-
-                println!("Distributing for deposit at {}: {:?}", Utc::now(), deposit);
-                let call = contract.distribute(token_to_distribute, to_address, amount, nonce);
-                let pending_tx = send_with_retry(|| call.send(), 3).await;
-                match pending_tx {
-                    Ok(tx) => {
-                        match tx.await {
-                            Ok(Some(receipt)) => {
-                                if receipt.status == Some(1.into()) {
-                                    println!(
-                                        "Distribution successful for deposit {} on {}: tx hash {:?}", 
-                                        deposit.deposit_id, distribution_chain, receipt.transaction_hash
-                                    );
-                                    if let Err(e) = db::update_deposit_status(&tx_pool, deposit.deposit_id, true, true).await {
-                                        eprintln!("Failed to update deposit status for deposit {}: {}", deposit.deposit_id, e);
-                                    }
-                                } else {
-                                    eprintln!(
-                                        "Distribution tx reverted for deposit {} on {}: tx hash {:?}", 
-                                        deposit.deposit_id, distribution_chain, receipt.transaction_hash
-                                    );
-                                }
-                            }
-                            Ok(None) => {
-                                eprintln!(
-                                    "Transaction receipt not found for deposit {} on {}", 
-                                    deposit.deposit_id, distribution_chain
-                                );
-                            }
-                            Err(e) => {
-                                eprintln!(
-                                    "Failed to confirm distribute tx for deposit {} on {}: {}", 
-                                    deposit.deposit_id, distribution_chain, e
-                                );
-                            }
-                        }
-                    }
-                    Err(e) => {
-                        eprintln!("Failed to send distribute tx for deposit {} on {}: {}", 
-                            deposit.deposit_id, distribution_chain, e);
-                    }
-                }
+// Operator review of held deposits and approvals, and fee reporting
+async fn operator_command(pool: &PgPool, args: &[String]) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    match (args[0].as_str(), args.get(1)) {
+        ("held", _) => print_deposits(pool, "held_for_review").await?,
+        ("awaiting-approval", _) => print_deposits(pool, "awaiting_approval").await?,
+        ("release", Some(deposit_id)) => {
+            let deposit_id: Uuid = deposit_id.parse()?;
+            if db::release_held_deposit(pool, deposit_id).await? {
+                println!("Released deposit {}; it will be distributed on the next pass", deposit_id);
+            } else {
+                eprintln!("Deposit {} is not held for review", deposit_id);
+                std::process::exit(1);
             }
-
-            sleep(Duration::from_secs(60)).await;
         }
-    });
-
-    // Shutdown Handling
-    tokio::signal::ctrl_c().await?;
-    println!("Received shutdown signal. Pausing contracts...");
-    pause_contracts(&holesky_client, &target_client, holesky_bridge_address, target_chain_bridge_address).await;
-    println!("Shutting down...");
+        ("approve", Some(deposit_id)) => approve_deposit(pool, deposit_id.parse()?).await?,
+        ("fees", _) => {
+            let totals = db::get_fee_totals(pool).await?;
+            if totals.is_empty() {
+                println!("No fees collected yet");
+            }
+            for total in totals {
+                println!("{} token {}: {} over {} deposits", total.chain_id, total.token_address, total.total, total.deposits);
+            }
+        }
+        _ => {
+            eprintln!("Usage: bridge-indexer held | awaiting-approval | release <deposit_id> | approve <deposit_id> | fees");
+            std::process::exit(2);
+        }
+    }
     Ok(())
 }
 
-// Retry Logic for Transactions
-async fn send_with_retry<F, Fut, P, T>(f: F, max_retries: usize) -> Result<T, Box<dyn std::error::Error + Send + Sync>>
-where
-    F: Fn() -> Fut,
-    Fut: std::future::Future<Output = Result<T, ethers::contract::ContractError<SignerMiddleware<P, LocalWallet>>>>,
-    P: ethers::providers::Middleware + 'static,
-{
-    let mut attempts = 0;
-    loop {
-        match f().await {
-            Ok(tx) => return Ok(tx),
-            Err(e) if attempts < max_retries => {
-                attempts += 1;
-                eprintln!("Attempt {} failed: {}. Retrying in 5s...", attempts, e);
-                sleep(Duration::from_secs(5)).await;
-            }
-            Err(e) => return Err(Box::new(e)),
-        }
+async fn print_deposits(pool: &PgPool, status: &str) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let deposits = db::get_deposits_by_status(pool, status).await?;
+    println!("{} deposits with status {}", deposits.len(), status);
+    for deposit in deposits {
+        println!(
+            "{} {} nonce {} amount {} to {}: {}",
+            deposit.deposit_id, deposit.chain_id, deposit.nonce, deposit.amount, deposit.to_address,
+            deposit.status_reason.as_deref().unwrap_or("no reason recorded")
+        );
     }
+    Ok(())
 }
 
-// Pause Contracts on Shutdown
-async fn pause_contracts(
-    holesky_client: &Arc<SignerMiddleware<Arc<Provider<Ws>>, LocalWallet>>,
-    target_client: &Arc<SignerMiddleware<Arc<Provider<Ws>>, LocalWallet>>,
-    holesky_bridge: H160,
-    target_bridge: H160,
-) {
-    let holesky_contract = TokenBridge::new(holesky_bridge, holesky_client.clone());
-    let target_contract = TokenBridge::new(target_bridge, target_client.clone());
-
-    if let Ok(tx) = holesky_contract.pause().send().await {
-        println!("Paused Holesky contract: {:?}", tx.tx_hash());
+// Sign an approval with the operator key (OPERATOR_* signer settings) and record it.
+// The deposit goes back to the processor once it has REQUIRED_APPROVALS valid approvals.
+async fn approve_deposit(pool: &PgPool, deposit_id: Uuid) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let policy = ApprovalPolicy::from_env()?;
+    let deposit = match db::get_deposit(pool, deposit_id).await? {
+        Some(deposit) if deposit.status == "awaiting_approval" => deposit,
+        _ => {
+            eprintln!("Deposit {} is not awaiting approval", deposit_id);
+            std::process::exit(1);
+        }
+    };
+    let operator = signer::operator_from_env()?;
+    if !policy.is_approver(operator.address()) {
+        eprintln!("Operator {} is not one of the APPROVERS", to_checksum(&operator.address(), None));
+        std::process::exit(1);
     }
-    if let Ok(tx) = target_contract.pause().send().await {
-        println!("Paused Base Sepolia contract: {:?}", tx.tx_hash());
+    // Approvals are a check on the distributor key, so that key cannot approve
+    for prefix in ["HOLESKY", "TARGET_CHAIN"] {
+        if signer::from_env(prefix, 1)?.address() == operator.address() {
+            eprintln!("Operator {} is the {} distributor; approve with a separate key", to_checksum(&operator.address(), None), prefix);
+            std::process::exit(1);
+        }
     }
-}
 
-// Unpause Contracts on Startup
-async fn unpause_contracts(
-    holesky_client: &Arc<SignerMiddleware<Arc<Provider<Ws>>, LocalWallet>>,
-    target_client: &Arc<SignerMiddleware<Arc<Provider<Ws>>, LocalWallet>>,
-    holesky_bridge: H160,
-    target_bridge: H160,
-) {
-    let holesky_contract = TokenBridge::new(holesky_bridge, holesky_client.clone());
-    let target_contract = TokenBridge::new(target_bridge, target_client.clone());
-
-    // Attempt to unpause Holesky contract
-    match holesky_contract.unpause().send().await {
-        Ok(tx) => println!("Unpaused Holesky contract: {:?}", tx.tx_hash()),
-        Err(e) => eprintln!("Failed to send unpause transaction for Holesky contract: {}", e),
+    let signature = operator.sign_message(approval::message(&deposit)).await?;
+    let approval = db::DepositApproval {
+        deposit_id,
+        approver: DbAddress(operator.address()),
+        signature: signature.to_string(),
+        created_at: None,
+    };
+    if !db::insert_deposit_approval(pool, &approval).await? {
+        println!("Operator {} already approved deposit {}", approval.approver, deposit_id);
     }
 
-    // Attempt to unpause Base Sepolia contract
-    match target_contract.unpause().send().await {
-        Ok(tx) => println!("Unpaused Base Sepolia contract: {:?}", tx.tx_hash()),
-        Err(e) => eprintln!("Failed to send unpause transaction for Base Sepolia contract: {}", e),
+    for recorded in db::get_deposit_approvals(pool, deposit_id).await? {
+        println!("Approved by {} at {:?}", recorded.approver, recorded.created_at);
     }
-}
\ No newline at end of file
+    let approvals = policy.valid_approvals(pool, &deposit).await?;
+    println!("Deposit {} has {} of {} required approvals", deposit_id, approvals, policy.required());
+    if approvals >= policy.required() {
+        if db::release_approved_deposit(pool, deposit_id).await? {
+            println!("Deposit {} approved; it will be distributed on the next pass", deposit_id);
+        } else {
+            println!("Deposit {} is no longer awaiting approval", deposit_id);
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,108 @@
+use ethers::prelude::*;
+use ethers::utils::to_checksum;
+use ethers::types::transaction::eip2718::TypedTransaction;
+use tokio::sync::Mutex;
+
+// Hands out account nonces locally for one signer on one chain, so several
+// distribute transactions can be in flight at once instead of one per receipt.
+pub struct NonceManager {
+    address: Address,
+    // Next nonce to assign; None means "resync from the chain before the next send"
+    next: Mutex<Option<U256>>,
+}
+
+// Node errors meaning the nonce we used is already taken by another mined or pending tx
+fn is_nonce_conflict(message: &str) -> bool {
+    let message = message.to_lowercase();
+    ["nonce too low", "replacement transaction underpriced", "nonce has already been used"]
+        .iter()
+        .any(|pattern| message.contains(pattern))
+}
+
+// Node errors meaning this exact signed tx is already in the mempool, e.g. when a broadcast
+// that timed out is retried. The tx went out; sending another one would pay twice.
+fn is_already_known(message: &str) -> bool {
+    let message = message.to_lowercase();
+    ["already known", "known transaction"].iter().any(|pattern| message.contains(pattern))
+}
+
+impl NonceManager {
+    pub fn new(address: Address) -> Self {
+        Self { address, next: Mutex::new(None) }
+    }
+
+    // Forget the local nonce; the next send reads the pending nonce from the chain.
+    // Used at startup and whenever no tx is in flight, which also closes any gap left
+    // by a tx the mempool dropped.
+    pub async fn resync(&self) {
+        *self.next.lock().await = None;
+    }
+
+    // Assign the next nonce to `tx`, sign and broadcast it, returning the tx hash and the tx
+    // as sent (with its nonce), so it can be replaced later.
+    // The lock is held across the broadcast, so nonces reach the node in order and a
+    // failed broadcast does not consume its nonce. A nonce conflict (e.g. after a
+    // restart with txs still pending) triggers one resync and retry.
+    pub async fn send<M: Middleware + 'static, S: Signer + 'static>(
+        &self,
+        client: &SignerMiddleware<M, S>,
+        mut tx: TypedTransaction,
+    ) -> Result<(H256, TypedTransaction), Box<dyn std::error::Error + Send + Sync>> {
+        let mut next = self.next.lock().await;
+        let mut resynced = false;
+        loop {
+            let nonce = match *next {
+                Some(nonce) => nonce,
+                None => client.get_transaction_count(self.address, Some(BlockNumber::Pending.into())).await?,
+            };
+            tx.set_nonce(nonce);
+            // Signed here rather than by the middleware, so the hash is known even when the
+            // node answers that it already has the tx
+            client.fill_transaction(&mut tx, None).await?;
+            let signature = client.signer().sign_transaction(&tx).await.map_err(|e| e.to_string())?;
+            let tx_hash = tx.hash(&signature);
+            match client.send_raw_transaction(tx.rlp_signed(&signature)).await {
+                Ok(_) => {
+                    *next = Some(nonce + 1);
+                    return Ok((tx_hash, tx));
+                }
+                Err(e) if is_already_known(&e.to_string()) => {
+                    println!("Tx {:?} with nonce {} is already known to the node ({})", tx_hash, nonce, e);
+                    *next = Some(nonce + 1);
+                    return Ok((tx_hash, tx));
+                }
+                Err(e) if !resynced && is_nonce_conflict(&e.to_string()) => {
+                    eprintln!("Nonce {} for {} is already used ({}). Resyncing from chain", nonce, to_checksum(&self.address, None), e);
+                    *next = None;
+                    resynced = true;
+                }
+                Err(e) => {
+                    *next = Some(nonce);
+                    return Err(e.into());
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resent_tx_is_not_a_nonce_conflict() {
+        for message in ["already known", "(code: -32000, message: already known)", "known transaction: 0xabc"] {
+            assert!(is_already_known(message), "{}", message);
+            assert!(!is_nonce_conflict(message), "{}", message);
+        }
+    }
+
+    #[test]
+    fn detects_nonce_conflicts() {
+        for message in ["nonce too low: next nonce 5, tx nonce 4", "replacement transaction underpriced", "Nonce has already been used"] {
+            assert!(is_nonce_conflict(message), "{}", message);
+            assert!(!is_already_known(message), "{}", message);
+        }
+        assert!(!is_nonce_conflict("insufficient funds for gas * price + value"));
+    }
+}
@@ -0,0 +1,831 @@
+use chrono::Utc;
+use ethers::prelude::*;
+use ethers::types::transaction::eip2718::TypedTransaction;
+use ethers::utils::{format_units, to_checksum};
+use sqlx::PgPool;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use tokio::task::JoinSet;
+use tokio::time::{sleep, Duration};
+use uuid::Uuid;
+use crate::abi::TokenBridge;
+use crate::alert;
+use crate::approval::ApprovalPolicy;
+use crate::bridge;
+use crate::db::{self, DbAddress, DbHash, Deposit};
+use crate::decimals::{self, DustPolicy};
+use crate::fee::FeeModel;
+use crate::gas::{Fees, GasPolicy};
+use crate::limits::RateLimits;
+use crate::liquidity::{LiquidityKey, LiquidityMonitor};
+use crate::nonce::NonceManager;
+use crate::screening::Screening;
+use crate::shutdown::{Shutdown, Stats};
+use crate::signer::Client;
+use crate::updates::Updates;
+
+// A distribute tx (and all its replacements) unknown to the node for this many blocks was
+// dropped from the mempool; its tracker gives up so the deposit can be sent again
+const DROPPED_AFTER_BLOCKS: u64 = 25;
+
+// A bridge contract the processor distributes on, with the nonce manager and gas policy of its signer
+pub struct Destination {
+    pub name: &'static str,
+    pub chain_id: &'static str,
+    pub contract: TokenBridge<Client>,
+    pub nonces: NonceManager,
+    pub gas: GasPolicy,
+}
+
+impl Destination {
+    pub fn new(name: &'static str, chain_id: &'static str, bridge_address: H160, client: Arc<Client>, gas: GasPolicy) -> Self {
+        let nonces = NonceManager::new(client.address());
+        Self { name, chain_id, contract: TokenBridge::new(bridge_address, client), nonces, gas }
+    }
+
+    fn provider(&self) -> Arc<Provider<Ws>> {
+        self.contract.client().inner().clone()
+    }
+}
+
+// Deposits of `source_token` on `source_chain_id` are paid out as `destination_token` on `destination`
+pub struct Route {
+    pub source_chain_id: &'static str,
+    // EVM chain id and bridge of the source, which namespace its nonces on the destination
+    pub source_evm_chain_id: u64,
+    pub source_bridge: H160,
+    pub source_token: H160,
+    pub destination: Arc<Destination>,
+    pub destination_token: H160,
+    pub source_decimals: u8,
+    pub destination_decimals: u8,
+    pub dust_policy: DustPolicy,
+    pub fee_model: FeeModel,
+    // Alert when the destination bridge holds less than this much of `destination_token` (0 disables)
+    pub low_liquidity_threshold: U256,
+    pub limits: RateLimits,
+    // Deposits above this amount need operator approval before they are distributed
+    pub approval_threshold: Option<U256>,
+}
+
+impl Route {
+    pub fn matches(&self, deposit: &Deposit) -> bool {
+        self.source_chain_id == deposit.chain_id
+            && deposit.token_address.0 == self.source_token
+    }
+
+    // A deposited amount in destination token units, and the dust it leaves in source units
+    pub fn convert(&self, amount: U256) -> Option<(U256, U256)> {
+        decimals::convert(amount, self.source_decimals, self.destination_decimals)
+    }
+}
+
+// A distribution awaiting its receipt, and the destination balance it will spend
+struct InFlight {
+    tx_hash: H256,
+    liquidity: LiquidityKey,
+    amount: U256,
+}
+
+pub struct Processor {
+    pool: PgPool,
+    routes: Vec<Route>,
+    stats: Arc<Stats>,
+    max_in_flight: usize,
+    max_attempts: i32,
+    approvals: ApprovalPolicy,
+    screening: Screening,
+    updates: Updates,
+}
+
+// What a background receipt tracker needs from the processor
+#[derive(Clone)]
+struct TrackerContext {
+    pool: PgPool,
+    stats: Arc<Stats>,
+    max_attempts: i32,
+    updates: Updates,
+}
+
+impl Processor {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        pool: PgPool,
+        routes: Vec<Route>,
+        stats: Arc<Stats>,
+        max_in_flight: usize,
+        max_attempts: i32,
+        approvals: ApprovalPolicy,
+        screening: Screening,
+        updates: Updates,
+    ) -> Self {
+        Self { pool, routes, stats, max_in_flight: max_in_flight.max(1), max_attempts, approvals, screening, updates }
+    }
+
+    fn tracker_context(&self) -> TrackerContext {
+        TrackerContext {
+            pool: self.pool.clone(),
+            stats: self.stats.clone(),
+            max_attempts: self.max_attempts,
+            updates: self.updates.clone(),
+        }
+    }
+
+    fn destinations(&self) -> Vec<Arc<Destination>> {
+        let mut destinations: Vec<Arc<Destination>> = Vec::new();
+        for route in &self.routes {
+            if !destinations.iter().any(|d| Arc::ptr_eq(d, &route.destination)) {
+                destinations.push(route.destination.clone());
+            }
+        }
+        destinations
+    }
+
+    // Transaction Processing Loop: sends distributions as soon as deposits are final and
+    // tracks their receipts in the background, up to `max_in_flight` at a time
+    pub async fn run(mut self, mut shutdown: Shutdown) {
+        let mut receipts: JoinSet<Uuid> = JoinSet::new();
+        let mut in_flight: HashMap<Uuid, InFlight> = HashMap::new();
+        let mut liquidity = LiquidityMonitor::default();
+
+        loop {
+            while let Some(result) = receipts.try_join_next() {
+                if let Ok(deposit_id) = result {
+                    in_flight.remove(&deposit_id);
+                }
+            }
+
+            println!("Checking for unprocessed deposits at {}", Utc::now());
+            let deposits = match db::get_unprocessed_deposits(&self.pool).await {
+                Ok(deposits) => deposits,
+                Err(e) => {
+                    eprintln!("Failed to fetch unprocessed deposits: {}. Retrying in 60s...", e);
+                    if shutdown.sleep(Duration::from_secs(60)).await {
+                        break;
+                    }
+                    continue;
+                }
+            };
+
+            if deposits.is_empty() {
+                println!("No unprocessed deposits found at {}", Utc::now());
+                if shutdown.sleep(Duration::from_secs(60)).await {
+                    break;
+                }
+                continue;
+            }
+
+            println!("Found {} unprocessed deposits at {}", deposits.len(), Utc::now());
+            self.screening.reload_if_changed();
+
+            // Distributions a destination bridge cannot pay from its token balance are held
+            let mut reserved: HashMap<LiquidityKey, U256> = HashMap::new();
+            for distribution in in_flight.values() {
+                let total = reserved.entry(distribution.liquidity).or_default();
+                *total = total.saturating_add(distribution.amount);
+            }
+            let pending: Vec<Deposit> = deposits.iter()
+                .filter(|deposit| !in_flight.contains_key(&deposit.deposit_id))
+                .cloned()
+                .collect();
+            let mut available = liquidity.check(&self.routes, &pending, &reserved).await;
+
+            // Hold distributions towards a paused bridge; an unreadable state counts as paused.
+            // With nothing in flight, resync nonces so a dropped tx cannot leave a gap behind.
+            let mut paused_chains = HashSet::new();
+            for destination in self.destinations() {
+                let paused = bridge::is_paused(&destination.contract).await.unwrap_or_else(|e| {
+                    eprintln!("Failed to read {} paused state: {}. Holding distributions", destination.name, e);
+                    true
+                });
+                if paused {
+                    paused_chains.insert(destination.chain_id);
+                }
+                if in_flight.is_empty() {
+                    destination.nonces.resync().await;
+                }
+            }
+
+            for deposit in deposits {
+                // Stop picking up new deposits once shutdown is requested
+                if shutdown.is_requested() {
+                    println!("Shutdown requested, leaving remaining deposits for the next run");
+                    break;
+                }
+                if in_flight.contains_key(&deposit.deposit_id) {
+                    continue;
+                }
+
+                // Wait for a slot before sending more
+                while receipts.len() >= self.max_in_flight {
+                    if let Some(Ok(deposit_id)) = receipts.join_next().await {
+                        in_flight.remove(&deposit_id);
+                    }
+                }
+
+                if let Some(distribution) = self.distribute(&deposit, &paused_chains, &mut available, &mut receipts).await {
+                    println!("Distribution tx {:?} for deposit {} is in flight", distribution.tx_hash, deposit.deposit_id);
+                    in_flight.insert(deposit.deposit_id, distribution);
+                }
+            }
+
+            if shutdown.sleep(Duration::from_secs(60)).await {
+                break;
+            }
+        }
+
+        if !receipts.is_empty() {
+            println!("Waiting for {} in-flight distributions to confirm", receipts.len());
+        }
+        while receipts.join_next().await.is_some() {}
+        println!("Transaction processor stopped");
+    }
+
+    // Check one deposit and broadcast its distribute tx, spawning a receipt tracker on success
+    async fn distribute(
+        &self,
+        deposit: &Deposit,
+        paused_chains: &HashSet<&'static str>,
+        available: &mut HashMap<LiquidityKey, U256>,
+        receipts: &mut JoinSet<Uuid>,
+    ) -> Option<InFlight> {
+        println!("Processing deposit at {}: {:?}", Utc::now(), deposit);
+        let route = match self.routes.iter().find(|route| route.matches(deposit)) {
+            Some(route) => route,
+            None if self.routes.iter().any(|route| route.source_chain_id == deposit.chain_id) => {
+                eprintln!("Unexpected token for {} deposit {}: {}", deposit.chain_id, deposit.deposit_id, deposit.token_address);
+                return None;
+            }
+            None => {
+                eprintln!("Unknown chain_id for deposit {}: {}", deposit.deposit_id, deposit.chain_id);
+                return None;
+            }
+        };
+        let destination = &route.destination;
+        let contract = &destination.contract;
+        let liquidity = (destination.chain_id, route.destination_token);
+
+        if paused_chains.contains(destination.chain_id) {
+            println!("Holding deposit {}: destination bridge is paused", deposit.deposit_id);
+            return None;
+        }
+
+        let to_address = deposit.to_address.0;
+
+        let Some(deposited) = db::from_numeric(&deposit.amount) else {
+            eprintln!("Amount {} of deposit {} is not a uint256", deposit.amount, deposit.deposit_id);
+            return None;
+        };
+
+        // Everything from here on works in destination token units
+        let rejection = match route.convert(deposited) {
+            None => Some(format!("amount {} overflows the destination token's {} decimals", deposited, route.destination_decimals)),
+            Some((amount, _)) if amount.is_zero() && !deposited.is_zero() => {
+                Some(format!("amount {} is below the destination token's smallest unit", deposited))
+            }
+            Some((_, dust)) if !dust.is_zero() && route.dust_policy == DustPolicy::Reject => Some(format!(
+                "amount {} has {} dust below the destination token's {} decimals",
+                deposited, dust, route.destination_decimals
+            )),
+            Some(_) => None,
+        };
+        if let Some(reason) = rejection {
+            eprintln!("Rejecting deposit {}: {}", deposit.deposit_id, reason);
+            if let Err(e) = db::set_deposit_status(&self.pool, deposit.deposit_id, "rejected", Some(&reason)).await {
+                eprintln!("Failed to reject deposit {}: {}", deposit.deposit_id, e);
+            }
+            self.updates.notify(deposit.deposit_id);
+            return None;
+        }
+        let (amount, dust) = route.convert(deposited).unwrap_or_default();
+
+        let Some(nonce) = db::from_numeric(&deposit.nonce) else {
+            eprintln!("Nonce {} of deposit {} is not a uint256", deposit.nonce, deposit.deposit_id);
+            return None;
+        };
+
+        // Refuse deposits that share a source identity or distribution id with another row
+        // (e.g. a nonce indexed twice): only one of them can ever be paid out
+        let distribution_id = bridge::distribution_id(route.source_evm_chain_id, route.source_bridge, nonce);
+        let id_hash = H256::from_uint(&distribution_id);
+        match db::find_colliding_deposits(&self.pool, deposit, id_hash).await {
+            Ok(others) if others.is_empty() => {}
+            Ok(others) => {
+                let reason = format!("distribution id {:?} is shared with deposits {:?}", id_hash, others);
+                alert::raise(&format!("Deposit {} from {} is ambiguous: {}", deposit.deposit_id, deposit.chain_id, reason)).await;
+                if let Err(e) = db::set_deposit_status(&self.pool, deposit.deposit_id, "ambiguous", Some(&reason)).await {
+                    eprintln!("Failed to mark deposit {} ambiguous: {}", deposit.deposit_id, e);
+                }
+                self.updates.notify(deposit.deposit_id);
+                return None;
+            }
+            Err(e) => {
+                eprintln!("Failed to check distribution id collisions for deposit {}: {}", deposit.deposit_id, e);
+                return None;
+            }
+        }
+
+        // A deposit indexed before distribution ids may have been sent with the raw nonce; check it as well
+        let processed = match contract.processed_deposits(distribution_id).call().await {
+            Ok(false) if deposit.legacy_nonce => contract.processed_deposits(nonce).call().await,
+            result => result,
+        };
+        let is_processed = match processed {
+            Ok(processed) => processed,
+            Err(e) => {
+                eprintln!("Failed to check processedDeposits for deposit {} (nonce {}): {}",
+                    deposit.deposit_id, nonce, e);
+                true // Assume processed to avoid repeated failures
+            }
+        };
+        if is_processed {
+            println!("Deposit with nonce {} already processed for deposit {} at {}",
+                nonce, deposit.deposit_id, Utc::now());
+            if let Err(e) = db::update_deposit_status(&self.pool, deposit.deposit_id, true, true).await {
+                eprintln!("Failed to update deposit status for deposit {}: {}", deposit.deposit_id, e);
+            }
+            self.updates.notify(deposit.deposit_id);
+            return None;
+        };
+
+        // A distribute tx from a previous run may still be in the mempool; keep tracking it
+        // (including fee bumps) instead of sending a second one
+        if let Some(tx_hash) = deposit.distribution_tx_hash {
+            let hash = tx_hash.0;
+            match contract.client().get_transaction(hash).await {
+                Ok(Some(tx)) if tx.block_number.is_none() => {
+                    println!("Distribution tx {} for deposit {} is still pending", tx_hash, deposit.deposit_id);
+                    receipts.spawn(track_distribution(
+                        self.tracker_context(),
+                        destination.clone(),
+                        deposit.deposit_id,
+                        <TypedTransaction as From<&Transaction>>::from(&tx),
+                        Fees::of(&tx),
+                        hash,
+                    ));
+                    if let Some(balance) = available.get_mut(&liquidity) {
+                        *balance = balance.saturating_sub(amount);
+                    }
+                    return Some(InFlight { tx_hash: hash, liquidity, amount });
+                }
+                Ok(_) => {}
+                Err(e) => {
+                    eprintln!("Failed to look up distribution tx {} for deposit {}: {}", tx_hash, deposit.deposit_id, e);
+                    return None;
+                }
+            }
+        }
+
+        // Screening: sanctioned or internal-only parties are never paid out
+        if let Some(reason) = self.screening.check(deposit) {
+            alert::raise(&format!("Deposit {} from {} blocked: {}", deposit.deposit_id, deposit.chain_id, reason)).await;
+            if let Err(e) = db::set_deposit_status(&self.pool, deposit.deposit_id, "blocked", Some(&reason)).await {
+                eprintln!("Failed to block deposit {}: {}", deposit.deposit_id, e);
+            }
+            self.updates.notify(deposit.deposit_id);
+            return None;
+        }
+
+        // Large deposits wait for enough operators to approve them
+        if route.approval_threshold.is_some_and(|threshold| amount > threshold) {
+            match self.approvals.valid_approvals(&self.pool, deposit).await {
+                Ok(approvals) if approvals >= self.approvals.required() => {}
+                Ok(approvals) => {
+                    let reason = format!(
+                        "amount {} is above the approval threshold of {} ({} of {} approvals)",
+                        amount, route.approval_threshold.unwrap_or_default(), approvals, self.approvals.required()
+                    );
+                    alert::raise(&format!("Deposit {} from {} awaits approval: {}", deposit.deposit_id, deposit.chain_id, reason)).await;
+                    if let Err(e) = db::set_deposit_status(&self.pool, deposit.deposit_id, "awaiting_approval", Some(&reason)).await {
+                        eprintln!("Failed to queue deposit {} for approval: {}", deposit.deposit_id, e);
+                    }
+                    self.updates.notify(deposit.deposit_id);
+                    return None;
+                }
+                Err(e) => {
+                    eprintln!("Failed to check approvals for deposit {}: {}", deposit.deposit_id, e);
+                    return None;
+                }
+            }
+        }
+
+        // Over-limit deposits wait for an operator instead of being retried every pass
+        if !deposit.limits_waived {
+            match route.limits.exceeded(&self.pool, deposit, amount).await {
+                Ok(None) => {}
+                Ok(Some(reason)) => {
+                    alert::raise(&format!("Deposit {} from {} held for review: {}", deposit.deposit_id, deposit.chain_id, reason)).await;
+                    if let Err(e) = db::set_deposit_status(&self.pool, deposit.deposit_id, "held_for_review", Some(&reason)).await {
+                        eprintln!("Failed to hold deposit {} for review: {}", deposit.deposit_id, e);
+                    }
+                    self.updates.notify(deposit.deposit_id);
+                    return None;
+                }
+                Err(e) => {
+                    eprintln!("Failed to check rate limits for deposit {}: {}", deposit.deposit_id, e);
+                    return None;
+                }
+            }
+        }
+
+        let balance = available.entry(liquidity).or_default();
+        if amount > *balance {
+            println!(
+                "Holding deposit {}: {} bridge has {} of token {} available, {} needed",
+                deposit.deposit_id, destination.name, balance, to_checksum(&route.destination_token, None), amount
+            );
+            return None;
+        }
+
+        let client = contract.client();
+        let fees = match destination.gas.fees(client.as_ref()).await {
+            Ok(fees) => fees,
+            Err(e) => {
+                eprintln!("Failed to estimate fees on {} for deposit {}: {}", destination.chain_id, deposit.deposit_id, e);
+                return None;
+            }
+        };
+        if destination.gas.exceeds_cap(&fees) {
+            println!(
+                "Deferring deposit {}: max fee {} gwei on {} is above the cap",
+                deposit.deposit_id, format_units(fees.max_fee_per_gas, "gwei").unwrap_or_default(), destination.chain_id
+            );
+            return None;
+        }
+
+        // Dry run: simulate with eth_call and estimate gas, so a revert costs no gas
+        let call = contract.distribute(route.destination_token, to_address, amount, distribution_id);
+        let simulation = match call.call().await {
+            Ok(()) => call.estimate_gas().await,
+            Err(e) => Err(e),
+        };
+        let gas = match simulation {
+            Ok(gas) => gas,
+            Err(e) => {
+                match bridge::revert_reason(&e) {
+                    Some(reason) if bridge::is_already_processed(&reason) => {
+                        println!("Distribution for deposit {} was already processed on {}; marking it processed", deposit.deposit_id, destination.chain_id);
+                        if let Err(e) = db::update_deposit_status(&self.pool, deposit.deposit_id, true, true).await {
+                            eprintln!("Failed to update deposit status for deposit {}: {}", deposit.deposit_id, e);
+                        }
+                        self.updates.notify(deposit.deposit_id);
+                    }
+                    Some(reason) => {
+                        eprintln!("Distribution for deposit {} would revert on {}: {}", deposit.deposit_id, destination.chain_id, reason);
+                        // Nothing was broadcast, so only permanent reasons end the deposit's retries
+                        let permanent = bridge::is_permanent_revert(&reason);
+                        if let Err(e) = db::record_distribution_failure(&self.pool, deposit.deposit_id, &reason, false, permanent).await {
+                            eprintln!("Failed to record revert reason for deposit {}: {}", deposit.deposit_id, e);
+                        }
+                        self.updates.notify(deposit.deposit_id);
+                    }
+                    None => eprintln!("Failed to simulate distribution for deposit {} on {}: {}", deposit.deposit_id, destination.chain_id, e),
+                }
+                return None;
+            }
+        };
+
+        // Bridge fee, deducted from what the recipient gets. The simulation above covered the
+        // full amount, so the smaller net transfer cannot revert where it did not.
+        let bridge_fee = route.fee_model.fee(amount, gas, fees.max_fee_per_gas);
+        if !bridge_fee.is_zero() && bridge_fee >= amount {
+            let reason = format!("{} fee {} leaves nothing of amount {}", route.fee_model.name(), bridge_fee, amount);
+            if route.fee_model.is_fixed() {
+                eprintln!("Rejecting deposit {}: {}", deposit.deposit_id, reason);
+                if let Err(e) = db::set_deposit_status(&self.pool, deposit.deposit_id, "rejected", Some(&reason)).await {
+                    eprintln!("Failed to reject deposit {}: {}", deposit.deposit_id, e);
+                }
+                self.updates.notify(deposit.deposit_id);
+            } else {
+                println!("Deferring deposit {}: {} at the current gas price", deposit.deposit_id, reason);
+            }
+            return None;
+        }
+        let amount = amount - bridge_fee;
+        let call = if bridge_fee.is_zero() {
+            call
+        } else {
+            contract.distribute(route.destination_token, to_address, amount, distribution_id)
+        };
+        if !bridge_fee.is_zero() {
+            let record = db::Fee {
+                deposit_id: deposit.deposit_id,
+                chain_id: destination.chain_id.to_string(),
+                token_address: DbAddress(route.destination_token),
+                fee_model: route.fee_model.name().to_string(),
+                amount: bridge_fee,
+                gross_amount: amount + bridge_fee,
+            };
+            if let Err(e) = db::record_fee(&self.pool, &record).await {
+                eprintln!("Failed to record fee for deposit {}: {}", deposit.deposit_id, e);
+                return None;
+            }
+        }
+
+        if let Err(e) = db::set_distribution_details(&self.pool, deposit.deposit_id, id_hash, amount, dust).await {
+            eprintln!("Failed to record distribution amount for deposit {}: {}", deposit.deposit_id, e);
+            return None;
+        }
+        if deposit.revert_reason.is_some() {
+            if let Err(e) = db::set_revert_reason(&self.pool, deposit.deposit_id, None).await {
+                eprintln!("Failed to clear revert reason for deposit {}: {}", deposit.deposit_id, e);
+            }
+        }
+
+        println!("Distributing for deposit at {}: {:?}", Utc::now(), deposit);
+        let mut tx = call.tx;
+        tx.set_gas(gas * 120 / 100); // 20% headroom over the estimate
+        fees.apply(&mut tx);
+        let (tx_hash, tx) = match send_with_retry(|| destination.nonces.send(client.as_ref(), tx.clone()), 3).await {
+            Ok(sent) => sent,
+            Err(e) => {
+                eprintln!("Failed to send distribute tx for deposit {} on {}: {}",
+                    deposit.deposit_id, destination.chain_id, e);
+                return None;
+            }
+        };
+        Stats::incr(&self.stats.distributions_sent);
+        if let Some(balance) = available.get_mut(&liquidity) {
+            *balance = balance.saturating_sub(amount);
+        }
+
+        // Persist the hash before waiting so a restart can find the in-flight tx
+        record_distribution_tx(&self.pool, destination.chain_id, deposit.deposit_id, &tx, &fees, tx_hash, None).await;
+        self.updates.notify(deposit.deposit_id);
+
+        receipts.spawn(track_distribution(
+            self.tracker_context(),
+            destination.clone(),
+            deposit.deposit_id,
+            tx,
+            fees,
+            tx_hash,
+        ));
+        Some(InFlight { tx_hash, liquidity, amount })
+    }
+}
+
+async fn record_distribution_tx(
+    pool: &PgPool,
+    chain_id: &str,
+    deposit_id: Uuid,
+    tx: &TypedTransaction,
+    fees: &Fees,
+    tx_hash: H256,
+    replaces: Option<H256>,
+) {
+    let record = db::DistributionTransaction {
+        tx_hash: DbHash(tx_hash),
+        deposit_id,
+        chain_id: chain_id.to_string(),
+        account_nonce: tx.nonce().map(|nonce| nonce.as_u64() as i64).unwrap_or_default(),
+        max_fee_per_gas: fees.max_fee_per_gas.to_string(),
+        max_priority_fee_per_gas: fees.max_priority_fee_per_gas.to_string(),
+        replaces_tx_hash: replaces.map(DbHash),
+    };
+    if let Err(e) = db::insert_distribution_transaction(pool, &record).await {
+        eprintln!("Failed to record distribution tx {} for deposit {}: {}", record.tx_hash, deposit_id, e);
+    }
+}
+
+// Receipt of whichever of the broadcast txs (original or replacements) got mined
+async fn mined_receipt<M: Middleware>(client: &M, tx_hashes: &[H256]) -> Result<Option<TransactionReceipt>, M::Error> {
+    for tx_hash in tx_hashes.iter().rev() {
+        if let Some(receipt) = client.get_transaction_receipt(*tx_hash).await? {
+            return Ok(Some(receipt));
+        }
+    }
+    Ok(None)
+}
+
+// True when the node knows none of the broadcast txs, neither pending nor mined
+async fn dropped<M: Middleware>(client: &M, tx_hashes: &[H256]) -> Result<bool, M::Error> {
+    for tx_hash in tx_hashes {
+        if client.get_transaction(*tx_hash).await?.is_some() {
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
+
+// Wait for a distribute tx to be mined and record the outcome on the deposit.
+// A tx not mined within REPLACE_AFTER_BLOCKS is replaced (same nonce, bumped fees)
+// as long as the bumped fees stay under the chain's cap. A tx dropped from the mempool
+// is given up on after DROPPED_AFTER_BLOCKS: the nonces are resynced and the deposit,
+// still pending, is sent again on a later pass.
+async fn track_distribution(
+    ctx: TrackerContext,
+    destination: Arc<Destination>,
+    deposit_id: Uuid,
+    mut tx: TypedTransaction,
+    mut fees: Fees,
+    tx_hash: H256,
+) -> Uuid {
+    let client = destination.contract.client();
+    let poll_interval = destination.provider().get_interval();
+    let distribution_chain = destination.chain_id;
+    let mut tx_hashes = vec![tx_hash];
+    let mut last_sent_block = client.get_block_number().await.map(|block| block.as_u64()).unwrap_or_default();
+    // First block at which the node knew none of the txs
+    let mut unknown_since: Option<u64> = None;
+
+    let receipt = loop {
+        match mined_receipt(client.as_ref(), &tx_hashes).await {
+            Ok(Some(receipt)) => break Some(receipt),
+            Ok(None) => {}
+            Err(e) => eprintln!("Failed to fetch receipt for deposit {} on {}: {}", deposit_id, distribution_chain, e),
+        }
+        sleep(poll_interval).await;
+
+        let current_block = match client.get_block_number().await {
+            Ok(block) => block.as_u64(),
+            Err(_) => continue,
+        };
+        match dropped(client.as_ref(), &tx_hashes).await {
+            Ok(true) => {
+                let since = *unknown_since.get_or_insert(current_block);
+                if current_block >= since + DROPPED_AFTER_BLOCKS {
+                    break None;
+                }
+            }
+            Ok(false) => unknown_since = None,
+            Err(e) => eprintln!("Failed to look up distribution txs for deposit {} on {}: {}", deposit_id, distribution_chain, e),
+        }
+
+        let policy = &destination.gas;
+        if policy.replace_after_blocks == 0 {
+            continue;
+        }
+        if current_block < last_sent_block + policy.replace_after_blocks {
+            continue;
+        }
+        last_sent_block = current_block;
+
+        let current_fees = match policy.fees(client.as_ref()).await {
+            Ok(current_fees) => current_fees,
+            Err(e) => {
+                eprintln!("Failed to estimate replacement fees for deposit {} on {}: {}", deposit_id, distribution_chain, e);
+                continue;
+            }
+        };
+        let bumped = policy.bump(&fees, &current_fees);
+        if policy.exceeds_cap(&bumped) {
+            println!(
+                "Not replacing distribution tx for deposit {} on {}: bumped max fee {} gwei is above the cap",
+                deposit_id, distribution_chain, format_units(bumped.max_fee_per_gas, "gwei").unwrap_or_default()
+            );
+            continue;
+        }
+        bumped.apply(&mut tx);
+        match client.send_transaction(tx.clone(), None).await {
+            Ok(pending_tx) => {
+                let replacement = pending_tx.tx_hash();
+                let replaced = *tx_hashes.last().unwrap_or(&tx_hash);
+                println!(
+                    "Replaced distribution tx {:?} for deposit {} on {} with {:?} (max fee {} gwei)",
+                    replaced, deposit_id, distribution_chain, replacement,
+                    format_units(bumped.max_fee_per_gas, "gwei").unwrap_or_default()
+                );
+                record_distribution_tx(&ctx.pool, distribution_chain, deposit_id, &tx, &bumped, replacement, Some(replaced)).await;
+                ctx.updates.notify(deposit_id);
+                tx_hashes.push(replacement);
+                fees = bumped;
+            }
+            // "nonce too low" here usually means an earlier tx was just mined; the next poll finds it
+            Err(e) => eprintln!("Failed to replace distribution tx for deposit {} on {}: {}", deposit_id, distribution_chain, e),
+        }
+    };
+
+    let Some(receipt) = receipt else {
+        alert::raise(&format!(
+            "Distribution tx {:?} for deposit {} on {} was dropped from the mempool; resyncing nonces and sending it again",
+            tx_hashes.last().unwrap_or(&tx_hash), deposit_id, distribution_chain
+        )).await;
+        destination.nonces.resync().await;
+        return deposit_id;
+    };
+
+    if tx_hashes.len() > 1 {
+        if let Err(e) = db::set_distribution_tx_hash(&ctx.pool, deposit_id, receipt.transaction_hash).await {
+            eprintln!("Failed to record mined distribution tx for deposit {}: {}", deposit_id, e);
+        }
+    }
+    if receipt.status == Some(1.into()) {
+        println!(
+            "Distribution successful for deposit {} on {}: tx hash {:?}",
+            deposit_id, distribution_chain, receipt.transaction_hash
+        );
+        Stats::incr(&ctx.stats.distributions_confirmed);
+        if let Err(e) = db::update_deposit_status(&ctx.pool, deposit_id, true, true).await {
+            eprintln!("Failed to update deposit status for deposit {}: {}", deposit_id, e);
+        }
+    } else {
+        let reason = replay_revert_reason(client.as_ref(), &receipt).await;
+        if bridge::is_already_processed(&reason) {
+            // Another distribution with the same id got there first, so the deposit is paid out
+            eprintln!(
+                "Distribution tx {:?} for deposit {} on {} reverted: {}. Marking the deposit processed",
+                receipt.transaction_hash, deposit_id, distribution_chain, reason
+            );
+            if let Err(e) = db::update_deposit_status(&ctx.pool, deposit_id, true, true).await {
+                eprintln!("Failed to update deposit status for deposit {}: {}", deposit_id, e);
+            }
+            ctx.updates.notify(deposit_id);
+            return deposit_id;
+        }
+        let permanent = bridge::is_permanent_revert(&reason);
+        match db::record_distribution_failure(&ctx.pool, deposit_id, &reason, true, permanent).await {
+            Ok(attempts) if permanent => eprintln!(
+                "Distribution tx reverted for deposit {} on {} (attempt {}): {}. Permanent failure, not retrying",
+                deposit_id, distribution_chain, attempts, reason
+            ),
+            Ok(attempts) if attempts >= ctx.max_attempts => {
+                eprintln!(
+                    "Distribution tx reverted for deposit {} on {} (attempt {}): {}. Giving up after {} attempts",
+                    deposit_id, distribution_chain, attempts, reason, ctx.max_attempts
+                );
+                if let Err(e) = db::mark_deposit_failed(&ctx.pool, deposit_id).await {
+                    eprintln!("Failed to mark deposit {} as failed: {}", deposit_id, e);
+                }
+            }
+            Ok(attempts) => eprintln!(
+                "Distribution tx reverted for deposit {} on {} (attempt {}): {}. Will retry",
+                deposit_id, distribution_chain, attempts, reason
+            ),
+            Err(e) => eprintln!("Failed to record revert reason for deposit {}: {}", deposit_id, e),
+        }
+    }
+    ctx.updates.notify(deposit_id);
+    deposit_id
+}
+
+// Find out why a mined distribute tx reverted. The node's call trace runs the tx on the exact
+// state it was mined against. Nodes without the debug API get an eth_call replay on the parent
+// block instead, which misses earlier txs of the same block (e.g. a pause or another
+// distribution draining the bridge), so that reason is marked as approximate.
+// Fees are left out of the replay so the call is not rejected for a base fee that has moved since.
+async fn replay_revert_reason(client: &Client, receipt: &TransactionReceipt) -> String {
+    if let Some(reason) = traced_revert_reason(client, receipt.transaction_hash).await {
+        return reason;
+    }
+    let tx = match client.get_transaction(receipt.transaction_hash).await {
+        Ok(Some(tx)) => tx,
+        Ok(None) => return "unknown (transaction not found for replay)".to_string(),
+        Err(e) => return format!("unknown (replay failed: {})", e),
+    };
+    if receipt.gas_used >= Some(tx.gas) {
+        return "out of gas".to_string();
+    }
+    let mut replay = TransactionRequest::new().from(tx.from).data(tx.input.clone()).value(tx.value).gas(tx.gas);
+    if let Some(to) = tx.to {
+        replay = replay.to(to);
+    }
+    let block = receipt.block_number.unwrap_or_default().saturating_sub(1.into());
+    let reason = match client.call(&replay.into(), Some(block.into())).await {
+        Err(e) => bridge::revert_reason(&ContractError::<Client>::from_middleware_error(e))
+            .unwrap_or_else(|| "unknown (replay failed without revert data)".to_string()),
+        Ok(_) => "unknown (replay did not revert)".to_string(),
+    };
+    format!("{} (replayed on block {}, without the txs before it in its block)", reason, block)
+}
+
+// Revert reason from a callTracer trace of the mined tx; None if the node does not serve
+// debug_traceTransaction or the trace carries no reason
+async fn traced_revert_reason(client: &Client, tx_hash: H256) -> Option<String> {
+    let options = GethDebugTracingOptions {
+        tracer: Some(GethDebugTracerType::BuiltInTracer(GethDebugBuiltInTracerType::CallTracer)),
+        ..Default::default()
+    };
+    let trace = match client.debug_trace_transaction(tx_hash, options).await {
+        Ok(trace) => trace,
+        Err(e) => {
+            eprintln!("Failed to trace distribution tx {:?}: {}. Replaying it with eth_call", tx_hash, e);
+            return None;
+        }
+    };
+    let GethTrace::Known(GethTraceFrame::CallTracer(frame)) = trace else {
+        return None;
+    };
+    frame.output.as_deref().and_then(bridge::decode_revert_data).or(frame.error)
+}
+
+// Retry Logic for Transactions
+async fn send_with_retry<F, Fut, T, E>(f: F, max_retries: usize) -> Result<T, E>
+where
+    F: Fn() -> Fut,
+    Fut: std::future::Future<Output = Result<T, E>>,
+    E: std::fmt::Display,
+{
+    let mut attempts = 0;
+    loop {
+        match f().await {
+            Ok(tx) => return Ok(tx),
+            Err(e) if attempts < max_retries => {
+                attempts += 1;
+                eprintln!("Attempt {} failed: {}. Retrying in 5s...", attempts, e);
+                sleep(Duration::from_secs(5)).await;
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
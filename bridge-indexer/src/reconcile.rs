@@ -0,0 +1,198 @@
+use chrono::Utc;
+use ethers::prelude::*;
+use ethers::utils::to_checksum;
+use serde_json::{json, Value};
+use sqlx::PgPool;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use crate::abi::{DepositFilter, TokenBridge};
+use crate::bridge::{self, LOG_CHUNK_BLOCKS};
+use crate::db::{self, Deposit};
+use crate::gaps::missing_nonces;
+use crate::signer::Client;
+
+// A bridge deployment checked by reconciliation; its deposits are distributed on `destination_chain_id`
+pub struct Deployment {
+    pub name: &'static str,
+    pub chain_id: &'static str,
+    pub evm_chain_id: u64,
+    pub contract: TokenBridge<Client>,
+    // Only deposits of this token are indexed
+    pub token: H160,
+    pub deploy_block: u64,
+    pub destination_chain_id: &'static str,
+}
+
+struct Discrepancies(Vec<Value>);
+
+impl Discrepancies {
+    fn add(&mut self, kind: &str, chain_id: &str, nonce: impl ToString, detail: String) {
+        println!("[{}] {} nonce {}: {}", kind, chain_id, nonce.to_string(), detail);
+        self.0.push(json!({ "kind": kind, "chain": chain_id, "nonce": nonce.to_string(), "detail": detail }));
+    }
+}
+
+// Compare the database with the chains and return the discrepancy report:
+// - each bridge's getNonce() against the deposits indexed for it (missing nonces)
+// - every deposit processed in the database against processedDeposits(nonce) on its destination
+// - every Distribution event on a destination against the deposit it should pay out
+pub async fn run(pool: &PgPool, deployments: &[Deployment]) -> Result<Value, Box<dyn std::error::Error + Send + Sync>> {
+    let mut discrepancies = Discrepancies(Vec::new());
+    let mut chains = Vec::new();
+    let mut deposits: HashMap<&str, Vec<Deposit>> = HashMap::new();
+    for deployment in deployments {
+        deposits.insert(deployment.chain_id, db::get_deposits_by_chain(pool, deployment.chain_id).await?);
+    }
+
+    // Indexed nonces: deposit nonces run from 0 to getNonce() - 1 without gaps, except for
+    // deposits in tokens the indexer does not bridge
+    for deployment in deployments {
+        println!("Reconciling {} bridge deposits", deployment.name);
+        let on_chain = deployment.contract.get_nonce().call().await?;
+        let blocks: BTreeMap<U256, u64> = deposits[deployment.chain_id].iter()
+            .filter_map(|deposit| Some((db::from_numeric(&deposit.nonce)?, deposit.block_number as u64)))
+            .collect();
+        let indexed: BTreeSet<U256> = blocks.keys().copied().collect();
+        let ignored: BTreeSet<U256> = db::get_ignored_nonces(pool, deployment.chain_id).await?
+            .iter()
+            .filter_map(db::from_numeric)
+            .collect();
+        let known: BTreeSet<U256> = indexed.union(&ignored).copied().collect();
+        let unindexed = missing_nonces(&known, on_chain);
+
+        // Look the unindexed nonces up on-chain to tell missed deposits from other-token ones
+        let found = find_deposits(deployment, &blocks, &unindexed).await?;
+        let mut other_token: Vec<U256> = ignored.range(..on_chain).copied().collect();
+        let mut missing = Vec::new();
+        for nonce in unindexed {
+            match found.get(&nonce) {
+                Some((event, _)) if event.token != deployment.token => other_token.push(nonce),
+                Some((_, tx_hash)) => {
+                    discrepancies.add("missing_deposit", deployment.chain_id, nonce, format!("deposit emitted in tx {:?} but not indexed", tx_hash));
+                    missing.push(nonce);
+                }
+                None => {
+                    discrepancies.add("missing_deposit", deployment.chain_id, nonce, "nonce below getNonce() with no Deposit log found".to_string());
+                    missing.push(nonce);
+                }
+            }
+        }
+        other_token.sort();
+        for nonce in indexed.range(on_chain..) {
+            discrepancies.add("unknown_deposit", deployment.chain_id, nonce, format!("indexed nonce is not below getNonce() = {}", on_chain));
+        }
+        chains.push(json!({
+            "chain": deployment.chain_id,
+            "on_chain_nonce": on_chain.to_string(),
+            "indexed_deposits": indexed.len(),
+            "highest_indexed_nonce": indexed.iter().next_back().map(|nonce| nonce.to_string()),
+            "missing_nonces": missing.iter().map(|nonce| nonce.to_string()).collect::<Vec<_>>(),
+            "other_token_nonces": other_token.iter().map(|nonce| nonce.to_string()).collect::<Vec<_>>(),
+        }));
+    }
+
+    // Deposits the database considers distributed must be processed on their destination
+    for deployment in deployments {
+        let Some(destination) = deployments.iter().find(|other| other.chain_id == deployment.destination_chain_id) else {
+            continue;
+        };
+        for deposit in deposits[deployment.chain_id].iter().filter(|deposit| deposit.processed == Some(true)) {
+            let Some(nonce) = db::from_numeric(&deposit.nonce) else { continue };
+            let distribution_id = bridge::distribution_id(deployment.evm_chain_id, deployment.contract.address(), nonce);
+            let mut processed = destination.contract.processed_deposits(distribution_id).call().await?;
+            // Deposits distributed before distribution ids existed were keyed by the raw nonce
+            if !processed && deposit.legacy_nonce {
+                processed = destination.contract.processed_deposits(nonce).call().await?;
+            }
+            if !processed {
+                discrepancies.add("not_processed_on_chain", deployment.chain_id, nonce, format!(
+                    "deposit {} is processed in the database but processedDeposits({:?}) is false on {}",
+                    deposit.deposit_id, H256::from_uint(&distribution_id), destination.chain_id
+                ));
+            }
+        }
+    }
+
+    // Every Distribution event must pay out an indexed deposit routed to that chain
+    for destination in deployments {
+        // Distribution events carry the distribution id, or the raw nonce for older distributions
+        let mut by_id: HashMap<U256, &Deposit> = HashMap::new();
+        let mut by_legacy_nonce: HashMap<U256, &Deposit> = HashMap::new();
+        for source in deployments.iter().filter(|source| source.destination_chain_id == destination.chain_id) {
+            for deposit in &deposits[source.chain_id] {
+                let Some(nonce) = db::from_numeric(&deposit.nonce) else { continue };
+                by_id.insert(bridge::distribution_id(source.evm_chain_id, source.contract.address(), nonce), deposit);
+                if deposit.legacy_nonce {
+                    by_legacy_nonce.insert(nonce, deposit);
+                }
+            }
+        }
+        let latest = destination.contract.client().get_block_number().await?.as_u64();
+        let mut from = destination.deploy_block;
+        let mut scanned = 0;
+        while from <= latest {
+            let to = (from + LOG_CHUNK_BLOCKS - 1).min(latest);
+            let events = destination.contract.distribution_filter().from_block(from).to_block(to).query_with_meta().await?;
+            for (event, meta) in events {
+                scanned += 1;
+                let deposit = by_id.get(&event.nonce).or_else(|| by_legacy_nonce.get(&event.nonce));
+                match deposit {
+                    None => discrepancies.add("unmatched_distribution", destination.chain_id, event.nonce, format!(
+                        "Distribution of {} to {} in tx {:?} has no indexed deposit", event.amount, to_checksum(&event.to, None), meta.transaction_hash
+                    )),
+                    Some(deposit) => {
+                        let expected = deposit.distribution_amount.as_ref().unwrap_or(&deposit.amount);
+                        if db::from_numeric(expected) != Some(event.amount) {
+                            discrepancies.add("amount_mismatch", destination.chain_id, event.nonce, format!(
+                                "Distribution in tx {:?} paid {} for deposit {}, expected {}",
+                                meta.transaction_hash, event.amount, deposit.deposit_id, expected
+                            ));
+                        }
+                        if deposit.processed != Some(true) {
+                            discrepancies.add("not_processed_in_db", destination.chain_id, event.nonce, format!(
+                                "deposit {} was distributed in tx {:?} but is not processed in the database",
+                                deposit.deposit_id, meta.transaction_hash
+                            ));
+                        }
+                    }
+                }
+            }
+            from = to + 1;
+        }
+        println!("Scanned {} Distribution events on the {} bridge from block {}", scanned, destination.name, destination.deploy_block);
+    }
+
+    Ok(json!({
+        "generated_at": Utc::now().to_rfc3339(),
+        "chains": chains,
+        "discrepancies": discrepancies.0,
+    }))
+}
+
+// Deposit events for the sorted `nonces`, keyed by nonce, with their transaction hash. The logs are
+// searched between the blocks of the indexed deposits around the lowest and highest nonce.
+async fn find_deposits(
+    deployment: &Deployment,
+    blocks: &BTreeMap<U256, u64>,
+    nonces: &[U256],
+) -> Result<HashMap<U256, (DepositFilter, H256)>, Box<dyn std::error::Error + Send + Sync>> {
+    let mut found = HashMap::new();
+    let Some((&first, &last)) = nonces.first().zip(nonces.last()) else {
+        return Ok(found);
+    };
+    let latest = deployment.contract.client().get_block_number().await?.as_u64();
+    let from = blocks.range(..first).next_back().map(|(_, block)| *block).unwrap_or(deployment.deploy_block);
+    let to = blocks.range(last + 1..).next().map(|(_, block)| *block).unwrap_or(latest);
+    let wanted: BTreeSet<U256> = nonces.iter().copied().collect();
+    let mut start = from;
+    while start <= to {
+        let end = (start + LOG_CHUNK_BLOCKS - 1).min(to);
+        let events = deployment.contract.deposit_filter().from_block(start).to_block(end).query_with_meta().await?;
+        for (event, meta) in events {
+            if wanted.contains(&event.nonce) {
+                found.insert(event.nonce, (event, meta.transaction_hash));
+            }
+        }
+        start = end + 1;
+    }
+    Ok(found)
+}
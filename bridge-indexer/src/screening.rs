@@ -0,0 +1,131 @@
+use ethers::prelude::*;
+use std::collections::{HashMap, HashSet};
+use std::env;
+use std::fs;
+use std::str::FromStr;
+use std::time::SystemTime;
+use crate::db::Deposit;
+
+// Address screening list, read from the file at SCREENING_LIST_PATH and reloaded whenever
+// the file changes. One entry per line, `#` starts a comment:
+//   deny 0x... [reason]   never distribute to or from this address
+//   allow 0x...           once any allow entry exists, only listed addresses may bridge
+#[derive(Debug, Default)]
+pub struct Screening {
+    path: Option<String>,
+    modified: Option<SystemTime>,
+    denied: HashMap<Address, String>,
+    allowed: HashSet<Address>,
+}
+
+type ScreeningList = (HashMap<Address, String>, HashSet<Address>);
+
+fn parse(contents: &str) -> Result<ScreeningList, String> {
+    let mut denied = HashMap::new();
+    let mut allowed = HashSet::new();
+    for (number, line) in contents.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or_default().trim();
+        if line.is_empty() {
+            continue;
+        }
+        // Fields may be separated by any run of whitespace
+        let (kind, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        let (address, reason) = rest.trim_start().split_once(char::is_whitespace).unwrap_or((rest.trim_start(), ""));
+        let address = Address::from_str(address)
+            .map_err(|_| format!("line {}: expected an address after '{}'", number + 1, kind))?;
+        match kind {
+            "deny" => {
+                let reason = Some(reason.trim()).filter(|reason| !reason.is_empty()).unwrap_or("denylisted");
+                denied.insert(address, reason.to_string());
+            }
+            "allow" => {
+                allowed.insert(address);
+            }
+            other => return Err(format!("line {}: unknown entry '{}' (expected deny or allow)", number + 1, other)),
+        }
+    }
+    Ok((denied, allowed))
+}
+
+impl Screening {
+    // An unset SCREENING_LIST_PATH disables screening; an unreadable list fails startup
+    pub fn from_env() -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
+        let mut screening = Screening { path: env::var("SCREENING_LIST_PATH").ok(), ..Default::default() };
+        if let Some(path) = screening.path.clone() {
+            let (denied, allowed) = parse(&fs::read_to_string(&path)?).map_err(|e| format!("{}: {}", path, e))?;
+            screening.modified = fs::metadata(&path).and_then(|metadata| metadata.modified()).ok();
+            screening.denied = denied;
+            screening.allowed = allowed;
+            println!("Loaded screening list {}: {} denied, {} allowed", path, screening.denied.len(), screening.allowed.len());
+        }
+        Ok(screening)
+    }
+
+    // Pick up edits to the list file; a broken edit keeps the previous list
+    pub fn reload_if_changed(&mut self) {
+        let Some(path) = self.path.as_deref() else { return };
+        let modified = fs::metadata(path).and_then(|metadata| metadata.modified()).ok();
+        if modified == self.modified {
+            return;
+        }
+        match fs::read_to_string(path).map_err(|e| e.to_string()).and_then(|contents| parse(&contents)) {
+            Ok((denied, allowed)) => {
+                println!("Reloaded screening list {}: {} denied, {} allowed", path, denied.len(), allowed.len());
+                self.denied = denied;
+                self.allowed = allowed;
+                self.modified = modified;
+            }
+            Err(e) => eprintln!("Failed to reload screening list {}: {}. Keeping the previous list", path, e),
+        }
+    }
+
+    // Why a deposit must not be distributed, or None if both parties pass screening
+    pub fn check(&self, deposit: &Deposit) -> Option<String> {
+        for (role, address) in [("sender", deposit.from_address), ("recipient", deposit.to_address)] {
+            if let Some(reason) = self.denied.get(&address.0) {
+                return Some(format!("{} {} is denied: {}", role, address, reason));
+            }
+            if !self.allowed.is_empty() && !self.allowed.contains(&address.0) {
+                return Some(format!("{} {} is not on the allowlist", role, address));
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DENIED: &str = "0x1111111111111111111111111111111111111111";
+    const ALLOWED: &str = "0x2222222222222222222222222222222222222222";
+
+    #[test]
+    fn parses_entries_comments_and_reasons() {
+        let contents = format!(
+            "# screening list\n\ndeny {DENIED} OFAC SDN list # added 2024-01-01\n  allow   {ALLOWED}\ndeny 0x3333333333333333333333333333333333333333\n"
+        );
+        let (denied, allowed) = parse(&contents).unwrap();
+        assert_eq!(denied.len(), 2);
+        assert_eq!(denied[&Address::from_str(DENIED).unwrap()], "OFAC SDN list");
+        assert_eq!(denied[&Address::repeat_byte(0x33)], "denylisted");
+        assert_eq!(allowed, HashSet::from([Address::from_str(ALLOWED).unwrap()]));
+    }
+
+    #[test]
+    fn empty_list_screens_nothing() {
+        let (denied, allowed) = parse("# nothing here\n\n").unwrap();
+        assert!(denied.is_empty());
+        assert!(allowed.is_empty());
+    }
+
+    #[test]
+    fn rejects_unknown_entries_and_bad_addresses() {
+        let unknown = parse(&format!("allow {ALLOWED}\nblock {DENIED}\n")).unwrap_err();
+        assert_eq!(unknown, "line 2: unknown entry 'block' (expected deny or allow)");
+        let missing = parse("deny\n").unwrap_err();
+        assert_eq!(missing, "line 1: expected an address after 'deny'");
+        let invalid = parse("deny 0x1234\n").unwrap_err();
+        assert_eq!(invalid, "line 1: expected an address after 'deny'");
+    }
+}
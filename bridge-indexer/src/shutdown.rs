@@ -0,0 +1,81 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use tokio::sync::watch;
+use tokio::time::{sleep, Duration};
+
+// Cooperative cancellation handle shared by every spawned task.
+// Tasks check it between units of work so nothing is cut off halfway.
+#[derive(Clone)]
+pub struct Shutdown {
+    rx: watch::Receiver<bool>,
+}
+
+pub fn channel() -> (watch::Sender<bool>, Shutdown) {
+    let (tx, rx) = watch::channel(false);
+    (tx, Shutdown { rx })
+}
+
+impl Shutdown {
+    pub fn is_requested(&self) -> bool {
+        *self.rx.borrow()
+    }
+
+    // Resolves once shutdown has been requested (or the sender is gone)
+    pub async fn requested(&mut self) {
+        let _ = self.rx.wait_for(|requested| *requested).await;
+    }
+
+    // Sleep for `duration`, waking early on shutdown. Returns true if shutdown was requested.
+    pub async fn sleep(&mut self, duration: Duration) -> bool {
+        tokio::select! {
+            _ = sleep(duration) => self.is_requested(),
+            _ = self.requested() => true,
+        }
+    }
+}
+
+// Wait for SIGINT (Ctrl-C) or SIGTERM and return the signal name
+pub async fn wait_for_signal() -> std::io::Result<&'static str> {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+        let mut sigterm = signal(SignalKind::terminate())?;
+        tokio::select! {
+            result = tokio::signal::ctrl_c() => result.map(|_| "SIGINT"),
+            _ = sigterm.recv() => Ok("SIGTERM"),
+        }
+    }
+    #[cfg(not(unix))]
+    {
+        tokio::signal::ctrl_c().await.map(|_| "Ctrl-C")
+    }
+}
+
+// Counters reported in the shutdown summary
+#[derive(Default)]
+pub struct Stats {
+    pub deposits_indexed: AtomicU64,
+    pub distributions_sent: AtomicU64,
+    pub distributions_confirmed: AtomicU64,
+}
+
+impl Stats {
+    pub fn incr(counter: &AtomicU64) {
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn add(counter: &AtomicU64, count: u64) {
+        counter.fetch_add(count, Ordering::Relaxed);
+    }
+
+    pub fn print_summary(&self, unfinished_tasks: usize, pending_deposits: Option<i64>) {
+        println!("Shutdown summary:");
+        println!("  Deposits indexed: {}", self.deposits_indexed.load(Ordering::Relaxed));
+        println!("  Distributions sent: {}", self.distributions_sent.load(Ordering::Relaxed));
+        println!("  Distributions confirmed: {}", self.distributions_confirmed.load(Ordering::Relaxed));
+        println!("  Tasks aborted at deadline: {}", unfinished_tasks);
+        match pending_deposits {
+            Some(count) => println!("  Deposits left to distribute: {}", count),
+            None => println!("  Deposits left to distribute: unknown"),
+        }
+    }
+}
@@ -0,0 +1,291 @@
+use async_trait::async_trait;
+use ethers::prelude::*;
+use ethers::types::transaction::eip2718::TypedTransaction;
+use ethers::types::transaction::eip712::Eip712;
+use ethers::utils::rlp::Rlp;
+use serde_json::{json, Value};
+use crate::config::{chain_var, prefixed_var, required_chain_var, required_prefixed_var};
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+
+// Signing happens while the chain's nonce lock is held, so a hung signer must not stall
+// every other distribution on that chain
+const REMOTE_SIGNER_TIMEOUT: Duration = Duration::from_secs(10);
+
+// Signer used for distribute and admin transactions on one chain, selected with SIGNER_TYPE:
+// - "private_key" (default, development only): raw PRIVATE_KEY from the environment
+// - "keystore": encrypted JSON keystore at KEYSTORE_PATH, password read from KEYSTORE_PASSWORD_FILE
+// - "remote": JSON-RPC signer at REMOTE_SIGNER_URL holding REMOTE_SIGNER_ADDRESS.
+//   Speaks eth_signTransaction / eth_sign as served by Web3Signer, Clef-style proxies,
+//   or any KMS-backed service exposing the same API.
+// Every setting can be overridden per chain with the chain's prefix, e.g. HOLESKY_PRIVATE_KEY.
+#[derive(Debug, Clone)]
+pub enum DistributorSigner {
+    Local(LocalWallet),
+    Remote(RemoteSigner),
+}
+
+// Provider + signer used for every transaction the indexer sends
+pub type Client = SignerMiddleware<Arc<Provider<Ws>>, DistributorSigner>;
+
+#[derive(Debug, Clone)]
+pub struct RemoteSigner {
+    client: reqwest::Client,
+    url: String,
+    address: Address,
+    chain_id: u64,
+}
+
+#[derive(Debug)]
+pub enum SignerError {
+    Wallet(WalletError),
+    Http(reqwest::Error),
+    Remote(String),
+}
+
+impl fmt::Display for SignerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SignerError::Wallet(e) => write!(f, "wallet error: {}", e),
+            SignerError::Http(e) => write!(f, "remote signer request failed: {}", e),
+            SignerError::Remote(e) => write!(f, "remote signer error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for SignerError {}
+
+impl From<WalletError> for SignerError {
+    fn from(e: WalletError) -> Self {
+        SignerError::Wallet(e)
+    }
+}
+
+impl From<reqwest::Error> for SignerError {
+    fn from(e: reqwest::Error) -> Self {
+        SignerError::Http(e)
+    }
+}
+
+// Build the signer configured in the environment for one destination chain.
+// `prefix` is the chain's env prefix (HOLESKY, TARGET_CHAIN) and `chain_id` its EVM chain id.
+pub fn from_env(prefix: &str, chain_id: u64) -> Result<DistributorSigner, Box<dyn std::error::Error + Send + Sync>> {
+    build(prefix, chain_id, true)
+}
+
+// Build the operator's approval signer from OPERATOR_* settings only. Falling back to the shared
+// settings would silently sign approvals with the distributor key.
+pub fn operator_from_env() -> Result<DistributorSigner, Box<dyn std::error::Error + Send + Sync>> {
+    build("OPERATOR", 1, false)
+}
+
+// `shared`: whether a `{PREFIX}_{NAME}` setting falls back to the shared `{NAME}`
+fn build(prefix: &str, chain_id: u64, shared: bool) -> Result<DistributorSigner, Box<dyn std::error::Error + Send + Sync>> {
+    let var = |name: &str| if shared { chain_var(prefix, name) } else { prefixed_var(prefix, name) };
+    let required = |name: &str| if shared { required_chain_var(prefix, name) } else { required_prefixed_var(prefix, name) };
+    let signer_type = var("SIGNER_TYPE").unwrap_or_else(|| "private_key".to_string());
+    let signer = match signer_type.as_str() {
+        "private_key" => {
+            let wallet = required("PRIVATE_KEY").parse::<LocalWallet>()?;
+            DistributorSigner::Local(wallet)
+        }
+        "keystore" => {
+            let path = required("KEYSTORE_PATH");
+            let password_file = required("KEYSTORE_PASSWORD_FILE");
+            let password = std::fs::read_to_string(&password_file)?;
+            let wallet = LocalWallet::decrypt_keystore(&path, password.trim_end_matches(['\r', '\n']))?;
+            DistributorSigner::Local(wallet)
+        }
+        "remote" => {
+            let url = required("REMOTE_SIGNER_URL");
+            let address: Address = required("REMOTE_SIGNER_ADDRESS").parse()?;
+            DistributorSigner::Remote(RemoteSigner::new(url, address))
+        }
+        other => return Err(format!("Unknown {}_SIGNER_TYPE: {} (expected private_key, keystore or remote)", prefix, other).into()),
+    };
+    Ok(signer.with_chain_id(chain_id))
+}
+
+impl RemoteSigner {
+    pub fn new(url: String, address: Address) -> Self {
+        Self::with_timeout(url, address, REMOTE_SIGNER_TIMEOUT)
+    }
+
+    fn with_timeout(url: String, address: Address, timeout: Duration) -> Self {
+        let client = reqwest::Client::builder()
+            .timeout(timeout)
+            .build()
+            .expect("failed to build the remote signer client");
+        Self { client, url, address, chain_id: 1 }
+    }
+
+    async fn request(&self, method: &str, params: Value) -> Result<Value, SignerError> {
+        let body = json!({ "jsonrpc": "2.0", "id": 1, "method": method, "params": params });
+        let response: Value = self.client
+            .post(&self.url)
+            .json(&body)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        if let Some(error) = response.get("error") {
+            return Err(SignerError::Remote(format!("{} failed: {}", method, error)));
+        }
+        response.get("result")
+            .cloned()
+            .ok_or_else(|| SignerError::Remote(format!("{} returned no result", method)))
+    }
+
+    async fn request_bytes(&self, method: &str, params: Value) -> Result<Bytes, SignerError> {
+        let result = self.request(method, params).await?;
+        result.as_str()
+            .and_then(|hex| hex.parse::<Bytes>().ok())
+            .ok_or_else(|| SignerError::Remote(format!("{} returned invalid hex: {}", method, result)))
+    }
+
+    async fn sign_transaction(&self, tx: &TypedTransaction) -> Result<Signature, SignerError> {
+        let mut tx = tx.clone();
+        tx.set_from(self.address);
+        if tx.chain_id().is_none() {
+            tx.set_chain_id(self.chain_id);
+        }
+        let mut params = serde_json::to_value(&tx).map_err(|e| SignerError::Remote(e.to_string()))?;
+        // ethers leaves chainId out of serialized requests, but the signer needs it for replay protection
+        params["chainId"] = json!(tx.chain_id());
+        let raw = self.request_bytes("eth_signTransaction", json!([params])).await?;
+        let (_, signature) = TypedTransaction::decode_signed(&Rlp::new(&raw))
+            .map_err(|e| SignerError::Remote(format!("undecodable signed transaction: {}", e)))?;
+        // Never trust the remote side to have signed what we asked for
+        if signature.recover(tx.sighash()).ok() != Some(self.address) {
+            return Err(SignerError::Remote("signature does not match the requested transaction".to_string()));
+        }
+        Ok(signature)
+    }
+
+    async fn sign_message(&self, message: &[u8]) -> Result<Signature, SignerError> {
+        let params = json!([self.address, Bytes::from(message.to_vec())]);
+        let raw = self.request_bytes("eth_sign", params).await?;
+        Signature::try_from(raw.as_ref()).map_err(|e| SignerError::Remote(format!("invalid signature: {}", e)))
+    }
+}
+
+#[async_trait]
+impl Signer for DistributorSigner {
+    type Error = SignerError;
+
+    async fn sign_message<S: Send + Sync + AsRef<[u8]>>(&self, message: S) -> Result<Signature, Self::Error> {
+        match self {
+            DistributorSigner::Local(wallet) => Ok(wallet.sign_message(message).await?),
+            DistributorSigner::Remote(remote) => remote.sign_message(message.as_ref()).await,
+        }
+    }
+
+    async fn sign_transaction(&self, tx: &TypedTransaction) -> Result<Signature, Self::Error> {
+        match self {
+            DistributorSigner::Local(wallet) => Ok(wallet.sign_transaction(tx).await?),
+            DistributorSigner::Remote(remote) => remote.sign_transaction(tx).await,
+        }
+    }
+
+    async fn sign_typed_data<T: Eip712 + Send + Sync>(&self, payload: &T) -> Result<Signature, Self::Error> {
+        match self {
+            DistributorSigner::Local(wallet) => Ok(wallet.sign_typed_data(payload).await?),
+            DistributorSigner::Remote(_) => Err(SignerError::Remote("typed data signing is not supported".to_string())),
+        }
+    }
+
+    fn address(&self) -> Address {
+        match self {
+            DistributorSigner::Local(wallet) => wallet.address(),
+            DistributorSigner::Remote(remote) => remote.address,
+        }
+    }
+
+    fn chain_id(&self) -> u64 {
+        match self {
+            DistributorSigner::Local(wallet) => wallet.chain_id(),
+            DistributorSigner::Remote(remote) => remote.chain_id,
+        }
+    }
+
+    fn with_chain_id<T: Into<u64>>(self, chain_id: T) -> Self {
+        match self {
+            DistributorSigner::Local(wallet) => DistributorSigner::Local(wallet.with_chain_id(chain_id)),
+            DistributorSigner::Remote(remote) => {
+                DistributorSigner::Remote(RemoteSigner { chain_id: chain_id.into(), ..remote })
+            }
+        }
+    }
+}
+
+// The local mock from examples/, so the remote signer is exercised over real HTTP
+#[cfg(test)]
+#[allow(dead_code)]
+#[path = "../examples/mock_remote_signer.rs"]
+mod mock_remote_signer;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+
+    const KEY: &str = "0x59c6995e998f97a5a0044966f0945389dc9e86dae88c7a8412f4603b6b78690d";
+
+    fn start_mock(wallet: LocalWallet) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(mock_remote_signer::serve(wallet, listener));
+        url
+    }
+
+    fn transfer() -> TypedTransaction {
+        Eip1559TransactionRequest::new()
+            .to(Address::repeat_byte(0x11))
+            .value(1_000u64)
+            .nonce(7u64)
+            .gas(21_000u64)
+            .max_fee_per_gas(2_000_000_000u64)
+            .max_priority_fee_per_gas(1_000_000_000u64)
+            .chain_id(17000u64)
+            .into()
+    }
+
+    #[tokio::test]
+    async fn remote_signature_recovers_to_the_mock_address() {
+        let wallet: LocalWallet = KEY.parse().unwrap();
+        let remote = RemoteSigner::new(start_mock(wallet.clone()), wallet.address());
+        let tx = transfer();
+
+        let signature = remote.sign_transaction(&tx).await.unwrap();
+        assert_eq!(signature.recover(tx.sighash()).unwrap(), wallet.address());
+
+        let signature = remote.sign_message(b"approve").await.unwrap();
+        assert_eq!(signature.recover("approve").unwrap(), wallet.address());
+    }
+
+    #[tokio::test]
+    async fn json_rpc_errors_are_returned_as_err() {
+        let wallet: LocalWallet = KEY.parse().unwrap();
+        let remote = RemoteSigner::new(start_mock(wallet.clone()), wallet.address());
+        let result = remote.request("eth_signTypedData_v4", json!([])).await;
+        assert!(matches!(result, Err(SignerError::Remote(_))));
+
+        // The mock signs with its own key, which is not the address we asked for
+        let remote = RemoteSigner::new(start_mock(wallet), Address::repeat_byte(0x22));
+        let result = remote.sign_transaction(&transfer()).await;
+        assert!(matches!(result, Err(SignerError::Remote(_))));
+    }
+
+    #[tokio::test]
+    async fn unresponsive_signer_times_out() {
+        // Accepts connections into the backlog but never answers
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let remote = RemoteSigner::with_timeout(url, Address::repeat_byte(0x22), Duration::from_millis(200));
+
+        let result = remote.sign_transaction(&transfer()).await;
+        assert!(matches!(result, Err(SignerError::Http(e)) if e.is_timeout()));
+    }
+}
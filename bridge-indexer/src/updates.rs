@@ -0,0 +1,21 @@
+use tokio::sync::mpsc;
+use uuid::Uuid;
+
+// Tells the API's status stream that a deposit changed, so its subscribers get the new state.
+// The listeners, finality loop and processor notify after writing a change; when the API is
+// disabled nothing receives and notifications are dropped.
+#[derive(Clone)]
+pub struct Updates {
+    tx: mpsc::UnboundedSender<Uuid>,
+}
+
+pub fn channel() -> (Updates, mpsc::UnboundedReceiver<Uuid>) {
+    let (tx, rx) = mpsc::unbounded_channel();
+    (Updates { tx }, rx)
+}
+
+impl Updates {
+    pub fn notify(&self, deposit_id: Uuid) {
+        let _ = self.tx.send(deposit_id);
+    }
+}